@@ -6,7 +6,7 @@ use super::square::Square;
 pub struct BitBoard(u64);
 
 impl BitBoard {
-    pub fn from(bitmap: u64) -> Self {
+    pub const fn from(bitmap: u64) -> Self {
         Self(bitmap)
     }
 
@@ -25,7 +25,7 @@ impl BitBoard {
         Square::get_by_index(self.0.trailing_zeros() as u8)
     }
 
-    pub fn value(self) -> u64 {
+    pub const fn value(self) -> u64 {
         self.0
     }
 
@@ -1,3 +1,5 @@
+use std::hash::{Hash, Hasher};
+
 use strum::IntoEnumIterator;
 
 use crate::move_generation::generator::MoveGenerator;
@@ -9,8 +11,9 @@ use super::game_state::{CastleAvailability, GameState};
 use super::history::History;
 use super::piece::{Color, Piece};
 use super::square::Square;
+use super::zobrist::ZOBRIST;
 
-#[derive()]
+#[derive(Clone)]
 pub struct Board {
     pub bitboards: [BitBoard; 12],
     pub occupancy: [BitBoard; 2],
@@ -19,6 +22,32 @@ pub struct Board {
     pub history: History,
 }
 
+/*
+ * Boards are equal if they describe the same position (everything FEN describes).
+ * Move history and the last unmade move stored in the game state are ignored.
+ */
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        let (state, other_state) = (&self.game_state, &other.game_state);
+
+        self.bitboards == other.bitboards
+            && state.active_color == other_state.active_color
+            && state.castle_settings == other_state.castle_settings
+            && state.en_passant_target == other_state.en_passant_target
+            && state.halfmove_clock == other_state.halfmove_clock
+            && state.fullmove_number == other_state.fullmove_number
+    }
+}
+
+impl Eq for Board {}
+
+// Zobrist key is derived from the same data equality looks at, so equal boards have equal hashes
+impl Hash for Board {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.zobrist_key().hash(state);
+    }
+}
+
 impl Board {
     pub fn new(
         bitboards: [BitBoard; 12],
@@ -26,7 +55,7 @@ impl Board {
         castle_settings: CastleAvailability,
         en_passant_target: Option<Square>,
         halfmove_clock: u8,
-        fullmove_number: u8,
+        fullmove_number: u16,
     ) -> Self {
         let piece_by_square = Self::init_piece_by_square(bitboards);
         let occupancy = Self::init_occupancy(bitboards);
@@ -39,14 +68,18 @@ impl Board {
             halfmove_clock,
             fullmove_number,
             next_move,
+            zobrist_key: 0,
         };
-        Self {
+        let mut board = Self {
             bitboards,
             occupancy,
             game_state,
             history,
             piece_by_square,
-        }
+        };
+        board.game_state.zobrist_key = ZOBRIST.hash(&board);
+
+        board
     }
 
     pub fn from_fen(&mut self, fen: &str) {
@@ -95,6 +128,28 @@ impl Board {
         mg.is_square_attacked(king_square, self.opponent_color(), self)
     }
 
+    /*
+     * Checks what FEN parsing can not: position must have one king of each color
+     * and the side that just moved can not be left in check.
+     * Move generation relies on both, so positions from untrusted input are validated first.
+     */
+    pub fn validate(&self, mg: &MoveGenerator) -> Result<(), String> {
+        for king in [Piece::WhiteKing, Piece::BlackKing] {
+            if self.bitboards[king as usize].count_ones() != 1 {
+                return Err(String::from("Position must have one king of each color"));
+            }
+        }
+
+        let opponent_king = self.bitboards
+            [Piece::WhiteKing.to_color(self.opponent_color()) as usize]
+            .lsb_bit_square();
+        if mg.is_square_attacked(opponent_king, self.active_color(), self) {
+            return Err(String::from("Side not to move is in check"));
+        }
+
+        Ok(())
+    }
+
     pub fn get_occupancies(&self, color: Color) -> BitBoard {
         if color == Color::Both {
             return self.occupancy[Color::White as usize] | self.occupancy[Color::Black as usize];
@@ -117,4 +172,86 @@ impl Board {
     pub fn castle_settings(&self) -> &CastleAvailability {
         &self.game_state.castle_settings
     }
+
+    pub fn zobrist_key(&self) -> u64 {
+        self.game_state.zobrist_key
+    }
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{
+        board_repr::fen::Fen,
+        comm::uci::UCI,
+        move_generation::{generator::MoveGenerator, move_list::MoveList, moves::MoveType},
+        _START_FEN, _TRICKY_POSITION,
+    };
+
+    #[test]
+    fn test_board_equality_ignores_history() {
+        let mg = MoveGenerator::shared();
+        let original = Fen::to_board(_TRICKY_POSITION);
+        let mut board = original.clone();
+        assert!(board == original);
+
+        let mut move_list = MoveList::new();
+        mg.generate_moves(&board, &mut move_list, MoveType::All);
+
+        for move_data in move_list {
+            if board.make_move(move_data, mg) {
+                assert!(board != original);
+                board.unmake_move();
+                assert!(board == original);
+            }
+        }
+
+        // Same position reached after a played move and set up from FEN
+        let mut played = Fen::to_board(_START_FEN);
+        let knight_out = UCI::parse_move("g1f3", &played, mg).unwrap();
+        played.make_move(knight_out, mg);
+        let from_fen = Fen::to_board("rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1");
+
+        assert!(played.history.len() != from_fen.history.len());
+        assert!(played == from_fen);
+        assert_eq!(
+            HashSet::from([played.clone(), from_fen.clone(), original]).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_move_counters() {
+        let mg = MoveGenerator::shared();
+        let mut board = Fen::to_board(_START_FEN);
+
+        for (uci, clock) in [
+            ("g1f3", 1),
+            ("g8f6", 2),
+            ("d2d4", 0),
+            ("f6e4", 1),
+            ("f3e5", 2),
+        ] {
+            let move_data = UCI::parse_move(uci, &board, mg).unwrap();
+            board.make_move(move_data, mg);
+            assert_eq!(board.game_state.halfmove_clock, clock, "{uci}");
+        }
+
+        let pawn_push = UCI::parse_move("e7e6", &board, mg).unwrap();
+        board.make_move(pawn_push, mg);
+        assert_eq!(
+            Fen::from_board(&board),
+            "rnbqkb1r/pppp1ppp/4p3/4N3/3Pn3/8/PPP1PPPP/RNBQKB1R w KQkq - 0 4"
+        );
+
+        // Long games go past 255 moves
+        let mut board = Fen::to_board("4k3/8/8/8/8/8/8/4K3 b - - 3 255");
+        let king_move = UCI::parse_move("e8d8", &board, mg).unwrap();
+        board.make_move(king_move, mg);
+        assert_eq!(Fen::from_board(&board), "3k4/8/8/8/8/8/8/4K3 w - - 4 256");
+    }
 }
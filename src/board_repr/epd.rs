@@ -0,0 +1,361 @@
+/*
+ * Extended Position Description.
+ * Four FEN fields followed by operations, each an opcode with operands and a closing ';':
+ *
+ * r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - bm Bb5; id "Ruy Lopez"; acd 12;
+ *
+ * Operands are typed: quoted strings, integers, floats, moves (for move opcodes, written in SAN)
+ * and bare symbols for anything else. Two numbers right after the four fields are read as
+ * halfmove clock and fullmove number, so six field FEN lines ('fen ;D1 20 ;D2 400') work too.
+ */
+
+use crate::move_generation::{generator::MoveGenerator, moves::Move, san::San};
+
+use super::{board::Board, fen::Fen};
+
+// Opcodes whose operands are moves in SAN. 'pv' moves are played one after another.
+const MOVE_OPCODES: [&str; 5] = ["bm", "am", "pm", "sm", "pv"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EpdOperand {
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Move(Move),
+    Symbol(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpdOperation {
+    pub opcode: String,
+    pub operands: Vec<EpdOperand>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Epd {
+    pub board: Board,
+    pub operations: Vec<EpdOperation>,
+}
+
+impl Epd {
+    pub fn new(board: Board) -> Self {
+        Self {
+            board,
+            operations: vec![],
+        }
+    }
+
+    pub fn parse(line: &str, mg: &MoveGenerator) -> Result<Self, String> {
+        let line = line.trim();
+        let mut fields = line.splitn(5, char::is_whitespace);
+        let mut fen: Vec<&str> = fields.by_ref().take(4).collect();
+        if fen.len() < 4 {
+            return Err(format!("EPD needs four position fields: {line}"));
+        }
+        let mut rest = fields.next().unwrap_or("").trim_start();
+
+        // Optional halfmove clock and fullmove number
+        let numbers: Vec<&str> = rest.split_whitespace().take(2).collect();
+        if numbers.len() == 2 && numbers.iter().all(|n| n.parse::<u8>().is_ok()) {
+            fen.extend(&numbers);
+            for number in numbers {
+                rest = rest.trim_start().strip_prefix(number).unwrap_or(rest);
+            }
+        }
+
+        let board = Fen::try_to_board(&fen.join(" "))?;
+        board.validate(mg)?;
+
+        let mut epd = Self::new(board);
+        for operation in split_operations(rest)? {
+            let operation = epd.parse_operation(&operation, mg)?;
+            epd.operations.push(operation);
+        }
+
+        Ok(epd)
+    }
+
+    fn parse_operation(
+        &self,
+        tokens: &[Token],
+        mg: &MoveGenerator,
+    ) -> Result<EpdOperation, String> {
+        let Some(Token::Bare(opcode)) = tokens.first() else {
+            return Err(String::from("EPD operation must start with an opcode"));
+        };
+        if !opcode.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            return Err(format!("Invalid EPD opcode: {opcode}"));
+        }
+
+        let mut board = self.board.clone();
+        let mut operands = vec![];
+        for token in &tokens[1..] {
+            let operand = match token {
+                Token::Quoted(text) => EpdOperand::String(text.clone()),
+                Token::Bare(text) if MOVE_OPCODES.contains(&opcode.as_str()) => {
+                    let move_data = San::parse_move(text, &board, mg)
+                        .ok_or_else(|| format!("Illegal move in '{opcode}': {text}"))?;
+                    if opcode == "pv" {
+                        board.make_legal_move(move_data);
+                    }
+                    EpdOperand::Move(move_data)
+                }
+                Token::Bare(text) => {
+                    if let Ok(integer) = text.parse() {
+                        EpdOperand::Integer(integer)
+                    } else if let Ok(float) = text.parse() {
+                        EpdOperand::Float(float)
+                    } else {
+                        EpdOperand::Symbol(text.clone())
+                    }
+                }
+            };
+            operands.push(operand);
+        }
+
+        Ok(EpdOperation {
+            opcode: opcode.clone(),
+            operands,
+        })
+    }
+
+    // Writes four position fields and all operations, moves are written in SAN
+    pub fn to_epd_string(&self, mg: &MoveGenerator) -> String {
+        let mut epd = Fen::position_fields(&self.board);
+        let mut board = self.board.clone();
+
+        for operation in &self.operations {
+            epd.push(' ');
+            epd.push_str(&operation.opcode);
+
+            for operand in &operation.operands {
+                epd.push(' ');
+                match operand {
+                    EpdOperand::Integer(value) => epd.push_str(&value.to_string()),
+                    EpdOperand::Float(value) => epd.push_str(&value.to_string()),
+                    EpdOperand::String(value) => epd.push_str(&format!("\"{value}\"")),
+                    EpdOperand::Symbol(value) => epd.push_str(value),
+                    EpdOperand::Move(move_data) => {
+                        epd.push_str(&San::from_move(*move_data, &board, mg));
+                        if operation.opcode == "pv" {
+                            board.make_legal_move(*move_data);
+                        }
+                    }
+                }
+            }
+            epd.push(';');
+
+            board = self.board.clone();
+        }
+
+        epd
+    }
+
+    pub fn operands(&self, opcode: &str) -> Option<&[EpdOperand]> {
+        self.operations
+            .iter()
+            .find(|operation| operation.opcode == opcode)
+            .map(|operation| operation.operands.as_slice())
+    }
+
+    // Replaces operands of the opcode, or adds the operation at the end
+    pub fn set_operation(&mut self, opcode: &str, operands: Vec<EpdOperand>) {
+        match self.operations.iter_mut().find(|o| o.opcode == opcode) {
+            Some(operation) => operation.operands = operands,
+            None => self.operations.push(EpdOperation {
+                opcode: opcode.to_string(),
+                operands,
+            }),
+        }
+    }
+
+    pub fn moves(&self, opcode: &str) -> Vec<Move> {
+        self.operands(opcode)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|operand| match operand {
+                EpdOperand::Move(move_data) => Some(*move_data),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn best_moves(&self) -> Vec<Move> {
+        self.moves("bm")
+    }
+
+    pub fn avoid_moves(&self) -> Vec<Move> {
+        self.moves("am")
+    }
+
+    pub fn string(&self, opcode: &str) -> Option<&str> {
+        match self.operands(opcode)?.first()? {
+            EpdOperand::String(value) | EpdOperand::Symbol(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn integer(&self, opcode: &str) -> Option<i64> {
+        match self.operands(opcode)?.first()? {
+            EpdOperand::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.string("id")
+    }
+
+    // Comments 'c0' .. 'c9'
+    pub fn comment(&self, index: u8) -> Option<&str> {
+        self.string(&format!("c{index}"))
+    }
+
+    // Analysis count depth
+    pub fn acd(&self) -> Option<i64> {
+        self.integer("acd")
+    }
+
+    // Centipawn evaluation
+    pub fn ce(&self) -> Option<i64> {
+        self.integer("ce")
+    }
+
+    // Perft node counts from 'D1' .. 'Dn' operations
+    pub fn perft_counts(&self) -> Vec<(i8, u64)> {
+        self.operations
+            .iter()
+            .filter_map(|operation| {
+                let depth = operation.opcode.strip_prefix('D')?.parse().ok()?;
+                match operation.operands.first()? {
+                    EpdOperand::Integer(nodes) => Some((depth, u64::try_from(*nodes).ok()?)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Bare(String),
+    Quoted(String),
+}
+
+// Splits operations on ';' outside quotes. Last operation may miss its ';'.
+fn split_operations(text: &str) -> Result<Vec<Vec<Token>>, String> {
+    let mut operations = vec![];
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => {
+                if !tokens.is_empty() {
+                    operations.push(std::mem::take(&mut tokens));
+                }
+            }
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => value.push(c),
+                        None => return Err(String::from("Unterminated EPD string operand")),
+                    }
+                }
+                tokens.push(Token::Quoted(value));
+            }
+            c if c.is_whitespace() => continue,
+            _ => {
+                let mut value = String::from(c);
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != ';') {
+                    value.push(c);
+                }
+                tokens.push(Token::Bare(value));
+            }
+        }
+    }
+    if !tokens.is_empty() {
+        operations.push(tokens);
+    }
+
+    Ok(operations)
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUY_LOPEZ: &str = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq -";
+
+    #[test]
+    fn test_parse_typed_operations() {
+        let mg = MoveGenerator::shared();
+        let line = format!(
+            "{RUY_LOPEZ} bm Bb5 Bc4; am Ba6?; id \"Ruy; Lopez\"; c0 \"main line\"; acd 12; ce -35; \
+             pv Bb5 a6 Ba4; fmvn 3; eval 0.25"
+        );
+        let epd = Epd::parse(&line, mg).unwrap();
+
+        let uci =
+            |moves: Vec<Move>| -> Vec<String> { moves.iter().map(|m| m.to_uci_string()).collect() };
+        assert_eq!(uci(epd.best_moves()), ["f1b5", "f1c4"]);
+        assert_eq!(uci(epd.avoid_moves()), ["f1a6"]);
+        assert_eq!(uci(epd.moves("pv")), ["f1b5", "a7a6", "b5a4"]);
+        assert_eq!(epd.id(), Some("Ruy; Lopez"));
+        assert_eq!(epd.comment(0), Some("main line"));
+        assert_eq!(epd.acd(), Some(12));
+        assert_eq!(epd.ce(), Some(-35));
+        assert_eq!(epd.operands("eval"), Some(&[EpdOperand::Float(0.25)][..]));
+
+        assert_eq!(
+            epd.to_epd_string(mg),
+            format!(
+                "{RUY_LOPEZ} bm Bb5 Bc4; am Ba6; id \"Ruy; Lopez\"; c0 \"main line\"; acd 12; \
+                 ce -35; pv Bb5 a6 Ba4; fmvn 3; eval 0.25;"
+            )
+        );
+        assert!(Epd::parse(&format!("{RUY_LOPEZ} bm Ke2 Bb6;"), mg).is_err());
+    }
+
+    #[test]
+    fn test_parse_perft_lines() {
+        let mg = MoveGenerator::shared();
+        let epd = Epd::parse(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ;D1 20 ;D2 400 ;D3 8902",
+            mg,
+        )
+        .unwrap();
+
+        assert_eq!(epd.perft_counts(), [(1, 20), (2, 400), (3, 8902)]);
+        assert_eq!(Fen::from_board(&epd.board), crate::_START_FEN);
+
+        let mut epd = Epd::parse("8/8/8/8/8/8/8/K6k b - - 7 40", mg).unwrap();
+        assert_eq!(epd.board.game_state.halfmove_clock, 7);
+        assert_eq!(epd.board.game_state.fullmove_number, 40);
+        epd.set_operation("D1", vec![EpdOperand::Integer(3)]);
+        assert_eq!(epd.to_epd_string(mg), "8/8/8/8/8/8/8/K6k b - - D1 3;");
+    }
+
+    #[test]
+    fn test_malformed_position_is_error() {
+        let mg = MoveGenerator::shared();
+
+        for line in [
+            "8/8/8/8/8/8/8/K6x w - - bm Kb1;",
+            "8/8/8/8/8/8/K6k w - - bm Kb1;",
+            "8/8/8/8/8/8/8/K7k w - - bm Kb1;",
+            "8/8/8/8/8/8/8/K6k x - - bm Kb1;",
+            "8/8/8/8/8/8/8/K6k w - z9 bm Kb1;",
+            // No white king, side not to move in check
+            "4k3/8/8/8/8/8/4P3/8 w - - bm e4;",
+            "4k3/8/8/8/8/8/8/K3R3 w - - bm Kb1;",
+        ] {
+            assert!(Epd::parse(line, mg).is_err(), "{line}");
+        }
+    }
+}
@@ -42,85 +42,322 @@ impl Fen {
     /// This method converts FEN notation to the Board struct used later by the engine
     /// Notice that this function does not support update version of spec
     /// (new version skips the en-passant parameter if it's "-")
+    /// Panics on invalid FEN, use 'try_to_board' for input that is not trusted
     pub fn to_board(fen: &str) -> Board {
-        let parts = fen.split(' ');
+        Self::try_to_board(fen).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    // Same as 'to_board', but invalid FEN is reported as an error
+    pub fn try_to_board(fen: &str) -> Result<Board, String> {
+        let parts = fen.split_whitespace();
         let mut active_color: Color = Color::White;
         let mut castle_settings = CastleAvailability::default();
         let mut en_passant_target: Option<Square> = None;
         let mut halfmove_clock = 0;
-        let mut fullmove_number: u8 = 1u8;
+        let mut fullmove_number: u16 = 1u16;
         let mut bitboards: BitBoardMap = BitBoardMap::default();
 
         for (i, part) in parts.enumerate() {
             match i {
-                0 => {
-                    let mut square_counter = 0u8;
-                    for rank in part.split('/') {
-                        for piece in rank.chars() {
-                            if piece.is_ascii_digit() {
-                                square_counter += piece.to_digit(10).unwrap() as u8;
-                                continue;
-                            }
-
-                            let board_piece =
-                                Piece::from_str(&piece.to_string()).unwrap_or_else(|_| {
-                                    panic!("Unknown piece value in FEN configuration: {}", piece)
-                                });
-                            let square = Square::get_by_index(square_counter);
-                            let piece_bitboard = square.get_bitboard();
-                            bitboards[board_piece.value() as usize] |= piece_bitboard;
-
-                            square_counter += 1;
-                        }
-                    }
-                }
+                0 => bitboards = Self::parse_placement(part)?,
                 1 => match part {
                     "w" => active_color = Color::White,
                     "b" => active_color = Color::Black,
-                    _ => panic!("Undefined color in FEN notation: {part}"),
+                    _ => return Err(format!("Undefined color in FEN notation: {part}")),
                 },
-                2 => {
-                    for c in part.chars() {
-                        match c {
-                            'K' => castle_settings.can_white_castle_king = true,
-                            'k' => castle_settings.can_black_castle_king = true,
-                            'Q' => castle_settings.can_white_castle_queen = true,
-                            'q' => castle_settings.can_black_castle_queen = true,
-                            _ => {}
-                        }
-                    }
-                }
+                2 => castle_settings = Self::parse_castling(part, &bitboards),
                 3 => {
                     if part == "-" {
                         continue;
                     }
 
-                    en_passant_target = Some(
-                        Square::from_str(&part.to_uppercase())
-                            .expect("Unknown en passant target square in Fen notation"),
-                    );
+                    let square = Square::from_str(&part.to_uppercase())
+                        .map_err(|_| format!("Unknown en passant target square in FEN: {part}"))?;
+                    en_passant_target = Some(square);
                 }
                 4 => {
-                    let clock: u8 = part.parse().expect("Clock value must be positive integer");
-                    halfmove_clock = clock;
+                    halfmove_clock = part
+                        .parse()
+                        .map_err(|_| format!("Clock value must be positive integer: {part}"))?;
                 }
                 5 => {
-                    let number: u8 = part
+                    fullmove_number = part
                         .parse()
-                        .expect("Fullmove number must be positive integer");
-                    fullmove_number = number;
+                        .map_err(|_| format!("Fullmove number must be positive integer: {part}"))?;
+                }
+                _ => {
+                    return Err(format!(
+                        "Unknown FEN configuration parameter. FEN: {fen}. Parameter: {part}"
+                    ))
                 }
-                _ => panic!("Unknown FEN configuration parameter. FEN: {fen}. Parameter: {part}"),
             }
         }
 
-        Board::new(
+        if fen.trim().is_empty() {
+            return Err(String::from("Empty FEN"));
+        }
+
+        Ok(Board::new(
             bitboards,
             active_color,
             castle_settings,
             en_passant_target,
             halfmove_clock,
             fullmove_number,
+        ))
+    }
+
+    // Piece placement field: eight ranks of eight squares each
+    fn parse_placement(part: &str) -> Result<BitBoardMap, String> {
+        let mut bitboards: BitBoardMap = BitBoardMap::default();
+        let ranks: Vec<&str> = part.split('/').collect();
+        if ranks.len() != 8 {
+            return Err(format!("FEN placement must have 8 ranks: {part}"));
+        }
+
+        for (rank_index, rank) in ranks.iter().enumerate() {
+            let mut file = 0u8;
+            for piece in rank.chars() {
+                if let Some(empty) = piece.to_digit(10).filter(|d| (1..=8).contains(d)) {
+                    file += empty as u8;
+                    continue;
+                }
+
+                let board_piece = Piece::from_str(&piece.to_string())
+                    .map_err(|_| format!("Unknown piece value in FEN configuration: {piece}"))?;
+                if file >= 8 {
+                    return Err(format!("FEN rank must have 8 squares: {rank}"));
+                }
+                let square = Square::from_file_and_rank(file, rank_index as u8);
+                bitboards[board_piece.value() as usize] |= square.get_bitboard();
+
+                file += 1;
+            }
+
+            if file != 8 {
+                return Err(format!("FEN rank must have 8 squares: {rank}"));
+            }
+        }
+
+        Ok(bitboards)
+    }
+
+    /*
+     * Castling field of FEN, X-FEN or Shredder-FEN.
+     * 'K' and 'Q' stand for the outermost rook on that side of the king, file letters ('HAha')
+     * name the castling rook directly. Rook files are shared by both sides.
+     */
+    fn parse_castling(part: &str, bitboards: &BitBoardMap) -> CastleAvailability {
+        let mut castle_settings = CastleAvailability::default();
+
+        for c in part.chars() {
+            let color = if c.is_ascii_uppercase() {
+                Color::White
+            } else {
+                Color::Black
+            };
+            let rank = if color == Color::White { 7 } else { 0 };
+            let king = bitboards[Piece::WhiteKing.to_color(color) as usize];
+            let rooks = bitboards[Piece::WhiteRook.to_color(color) as usize];
+            let king_file = match king.empty() {
+                true => castle_settings.king_file,
+                false => king.lsb_bit_square().file(),
+            };
+            let mut rook_files = (0..8u8)
+                .filter(|file| rooks.get_bit_value(Square::from_file_and_rank(*file, rank)));
+
+            let (king_side, rook_file) = match c.to_ascii_uppercase() {
+                'K' => (
+                    true,
+                    rook_files.filter(|f| *f > king_file).max().unwrap_or(7),
+                ),
+                'Q' => (false, rook_files.find(|f| *f < king_file).unwrap_or(0)),
+                file @ 'A'..='H' => {
+                    let file = file as u8 - b'A';
+                    (file > king_file, file)
+                }
+                _ => continue,
+            };
+
+            castle_settings.king_file = king_file;
+            match (color, king_side) {
+                (Color::White, true) => castle_settings.can_white_castle_king = true,
+                (Color::White, false) => castle_settings.can_white_castle_queen = true,
+                (_, true) => castle_settings.can_black_castle_king = true,
+                (_, false) => castle_settings.can_black_castle_queen = true,
+            }
+            if king_side {
+                castle_settings.king_rook_file = rook_file;
+            } else {
+                castle_settings.queen_rook_file = rook_file;
+            }
+        }
+
+        castle_settings
+    }
+
+    // Converts the Board back to all six FEN fields
+    pub fn from_board(board: &Board) -> String {
+        format!(
+            "{} {} {}",
+            Self::position_fields(board),
+            board.game_state.halfmove_clock,
+            board.game_state.fullmove_number
         )
     }
+
+    // First four FEN fields: piece placement, active color, castling rights and en-passant target
+    pub fn position_fields(board: &Board) -> String {
+        let mut fen = String::new();
+
+        for rank in 0..8u8 {
+            let mut empty_squares = 0;
+            for file in 0..8u8 {
+                let piece = board.piece_by_square[(rank * 8 + file) as usize];
+                if piece.is_none() {
+                    empty_squares += 1;
+                    continue;
+                }
+                if empty_squares > 0 {
+                    fen.push_str(&empty_squares.to_string());
+                    empty_squares = 0;
+                }
+                fen.push_str(&piece.to_fen_string());
+            }
+            if empty_squares > 0 {
+                fen.push_str(&empty_squares.to_string());
+            }
+            if rank < 7 {
+                fen.push('/');
+            }
+        }
+
+        fen.push_str(match board.active_color() {
+            Color::Black => " b ",
+            _ => " w ",
+        });
+
+        // X-FEN: outermost castling rook is written as 'K' or 'Q', an inner one by its file
+        let castle_settings = board.castle_settings();
+        for (available, color, king_side) in [
+            (castle_settings.can_white_castle_king, Color::White, true),
+            (castle_settings.can_white_castle_queen, Color::White, false),
+            (castle_settings.can_black_castle_king, Color::Black, true),
+            (castle_settings.can_black_castle_queen, Color::Black, false),
+        ] {
+            if !available {
+                continue;
+            }
+
+            let rook = Piece::WhiteRook.to_color(color);
+            let rank = if color == Color::White { 7 } else { 0 };
+            let (rook_file, mut outer_files) = match king_side {
+                true => (
+                    castle_settings.king_rook_file,
+                    castle_settings.king_rook_file + 1..8,
+                ),
+                false => (
+                    castle_settings.queen_rook_file,
+                    0..castle_settings.queen_rook_file,
+                ),
+            };
+            let is_outermost =
+                outer_files.all(|file| board.piece_by_square[(rank * 8 + file) as usize] != rook);
+
+            let c = match (is_outermost, king_side) {
+                (true, true) => 'K',
+                (true, false) => 'Q',
+                (false, _) => (b'A' + rook_file) as char,
+            };
+            fen.push(match color {
+                Color::White => c,
+                _ => c.to_ascii_lowercase(),
+            });
+        }
+        if castle_settings.bits() == 0 {
+            fen.push('-');
+        }
+
+        match board.game_state.en_passant_target {
+            Some(square) => fen.push_str(&format!(" {}", square.to_string().to_lowercase())),
+            None => fen.push_str(" -"),
+        }
+
+        fen
+    }
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use crate::{
+        _CASTLING, _EMPTY, _EN_PASSANT, _POSITION_3, _POSITION_4, _POSITION_5, _POSITION_6,
+        _PROMOTION, _START_FEN, _TRICKY_POSITION,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_fen_round_trip() {
+        for fen in [
+            _START_FEN,
+            _TRICKY_POSITION,
+            _POSITION_3,
+            _POSITION_4,
+            _POSITION_5,
+            _POSITION_6,
+            _EN_PASSANT,
+            _EMPTY,
+            _PROMOTION,
+            _CASTLING,
+        ] {
+            assert_eq!(Fen::from_board(&Fen::to_board(fen)), fen);
+        }
+    }
+
+    #[test]
+    fn test_invalid_fen_is_error() {
+        assert!(Fen::try_to_board(_START_FEN).is_ok());
+
+        for fen in [
+            "",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNRR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR - KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e9 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - -1 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 extra",
+        ] {
+            assert!(Fen::try_to_board(fen).is_err(), "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_chess960_castling_fields() {
+        // Shredder-FEN names rook files, X-FEN only does it for inner rooks
+        let board = Fen::to_board("rk2r2r/8/8/8/8/8/8/RK2R2R w EAea - 0 1");
+        let castle_settings = board.castle_settings();
+        assert_eq!(castle_settings.king_file, 1);
+        assert_eq!(castle_settings.king_rook_file, 4);
+        assert_eq!(castle_settings.queen_rook_file, 0);
+        assert_eq!(
+            Fen::from_board(&board),
+            "rk2r2r/8/8/8/8/8/8/RK2R2R w EQeq - 0 1"
+        );
+        assert!(Fen::to_board("rk2r2r/8/8/8/8/8/8/RK2R2R w EQeq - 0 1") == board);
+
+        let board =
+            Fen::to_board("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9");
+        let castle_settings = board.castle_settings();
+        assert_eq!(castle_settings.king_file, 6);
+        assert_eq!(castle_settings.king_rook_file, 7);
+        assert_eq!(castle_settings.queen_rook_file, 5);
+        assert_eq!(
+            Fen::from_board(&board),
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9"
+        );
+    }
 }
@@ -2,35 +2,90 @@ use crate::move_generation::moves::Move;
 
 use super::{piece::Color, square::Square};
 
-#[derive(Default, Clone, Copy)]
+/*
+ * Castle rights of both sides.
+ * Files of the king and the castling rooks are needed for Chess960, where they depend on the
+ * starting position. Both sides share them, as Chess960 positions are mirrored.
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct CastleAvailability {
     pub can_white_castle_queen: bool,
     pub can_white_castle_king: bool,
 
     pub can_black_castle_queen: bool,
     pub can_black_castle_king: bool,
+
+    pub king_file: u8,
+    pub king_rook_file: u8,
+    pub queen_rook_file: u8,
+}
+
+impl Default for CastleAvailability {
+    fn default() -> Self {
+        Self {
+            can_white_castle_queen: false,
+            can_white_castle_king: false,
+            can_black_castle_queen: false,
+            can_black_castle_king: false,
+            king_file: 4,
+            king_rook_file: 7,
+            queen_rook_file: 0,
+        }
+    }
 }
 
 impl CastleAvailability {
+    // Clears castle rights lost when a piece leaves or is captured on the square
     pub fn set_by_square(&mut self, square: Square) {
-        match square {
-            // White
-            Square::A1 => self.can_white_castle_queen = false,
-            Square::E1 => {
-                self.can_white_castle_king = false;
-                self.can_white_castle_queen = false;
-            }
-            Square::H1 => self.can_white_castle_king = false,
-
-            // Black
-            Square::A8 => self.can_black_castle_queen = false,
-            Square::E8 => {
-                self.can_black_castle_king = false;
-                self.can_black_castle_queen = false;
-            }
-            Square::H8 => self.can_black_castle_king = false,
-            _ => (),
+        let (king_side, queen_side) = match square.rank() {
+            7 => (
+                &mut self.can_white_castle_king,
+                &mut self.can_white_castle_queen,
+            ),
+            0 => (
+                &mut self.can_black_castle_king,
+                &mut self.can_black_castle_queen,
+            ),
+            _ => return,
+        };
+
+        let file = square.file();
+        if file == self.king_file {
+            *king_side = false;
+            *queen_side = false;
+        }
+        if file == self.king_rook_file {
+            *king_side = false;
         }
+        if file == self.queen_rook_file {
+            *queen_side = false;
+        }
+    }
+
+    // King always lands on the 'g' or 'c' file, whatever the position of the rook
+    pub fn rook_square(&self, king_target: Square) -> Square {
+        let file = match king_target.file() {
+            6 => self.king_rook_file,
+            _ => self.queen_rook_file,
+        };
+        Square::from_file_and_rank(file, king_target.rank())
+    }
+
+    // Rook lands on the 'f' or 'd' file, next to the king
+    pub fn rook_target(&self, king_target: Square) -> Square {
+        let file = match king_target.file() {
+            6 => 5,
+            _ => 3,
+        };
+        Square::from_file_and_rank(file, king_target.rank())
+    }
+
+    // Packs castle rights into 4 bits. Used as an index into zobrist castling keys.
+    pub fn bits(&self) -> u8 {
+        (self.can_white_castle_king as u8)
+            | (self.can_white_castle_queen as u8) << 1
+            | (self.can_black_castle_king as u8) << 2
+            | (self.can_black_castle_queen as u8) << 3
     }
 }
 
@@ -40,6 +95,7 @@ pub struct GameState {
     pub castle_settings: CastleAvailability,
     pub en_passant_target: Option<Square>,
     pub halfmove_clock: u8,
-    pub fullmove_number: u8,
+    pub fullmove_number: u16,
     pub next_move: Move,
+    pub zobrist_key: u64,
 }
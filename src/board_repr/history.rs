@@ -1,33 +1,33 @@
 use super::game_state::GameState;
 
-const MAX_MOVES: usize = 2048;
-
+// Grows with the game, so copying a board only copies moves that were actually played
+#[derive(Clone, Default)]
 pub struct History {
-    pub history: [GameState; MAX_MOVES],
-    pub count: usize,
-}
-
-impl Default for History {
-    fn default() -> Self {
-        Self::new()
-    }
+    pub history: Vec<GameState>,
 }
 
 impl History {
     pub fn new() -> Self {
         Self {
-            history: [GameState::default(); MAX_MOVES],
-            count: 0,
+            history: Vec::with_capacity(256),
         }
     }
 
     pub fn push(&mut self, game_state: GameState) {
-        self.history[self.count] = game_state;
-        self.count += 1;
+        self.history.push(game_state);
     }
 
     pub fn pop(&mut self) -> GameState {
-        self.count -= 1;
-        self.history[self.count]
+        self.history
+            .pop()
+            .expect("Can not unmake move, history is empty")
+    }
+
+    pub fn len(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.history.is_empty()
     }
 }
@@ -7,6 +7,7 @@ use super::{
     board::Board,
     piece::{Color, Piece},
     square::Square,
+    zobrist::ZOBRIST,
 };
 
 /*
@@ -19,6 +20,7 @@ impl Board {
         self.bitboards[piece as usize] |= square_bb;
         self.piece_by_square[square as usize] = piece;
         self.occupancy[piece.color() as usize] |= square_bb;
+        self.game_state.zobrist_key ^= ZOBRIST.piece_key(piece as usize, square);
     }
 
     pub fn remove_piece(&mut self, square: Square, piece: Piece) {
@@ -26,6 +28,7 @@ impl Board {
         self.bitboards[piece as usize] &= !square_bb;
         self.piece_by_square[square as usize] = Piece::None;
         self.occupancy[piece.color() as usize] &= !square_bb;
+        self.game_state.zobrist_key ^= ZOBRIST.piece_key(piece as usize, square);
     }
 
     pub fn move_piece(&mut self, source_square: Square, target_square: Square, piece: Piece) {
@@ -50,6 +53,26 @@ impl Board {
 impl Board {
     #[inline(always)]
     pub fn make_move(&mut self, move_data: Move, mg: &MoveGenerator) -> bool {
+        self.make_legal_move(move_data);
+
+        // If king of the side that moved is under attack - move is not legal - unmake move
+        let king_square = self.bitboards[Piece::WhiteKing.to_color(self.opponent_color()) as usize]
+            .lsb_bit_square();
+        let is_legal = !mg.is_square_attacked(king_square, self.active_color(), self);
+
+        if !is_legal {
+            self.unmake_move();
+        }
+
+        is_legal
+    }
+
+    /*
+     * Applies move without checking if it leaves own king in check.
+     * Only for moves that are known to be legal, e.g. from 'MoveGenerator::generate_legal_moves'.
+     */
+    #[inline(always)]
+    pub fn make_legal_move(&mut self, move_data: Move) {
         // Create history record of the move played
         let mut game_state = self.game_state;
         game_state.next_move = move_data;
@@ -69,8 +92,15 @@ impl Board {
         let is_capture = !captured_piece.is_none();
         let is_promotion = !promoted_piece.is_none();
 
-        // Assume that move is not capture and update halfmove clock
-        self.game_state.halfmove_clock += 1;
+        // Castle rights and en-passant square are going to change, so hash them out for now
+        self.game_state.zobrist_key ^= ZOBRIST.castling_key(self.castle_settings());
+        self.game_state.zobrist_key ^= ZOBRIST.en_passant_key(self.game_state.en_passant_target);
+
+        // Pawn moves and captures reset the halfmove clock, any other move advances it
+        self.game_state.halfmove_clock = self.game_state.halfmove_clock.saturating_add(1);
+        if piece.is_pawn() {
+            self.game_state.halfmove_clock = 0;
+        }
 
         // En-passant square valid only for one move, so clear the square here
         if self.game_state.en_passant_target.is_some() {
@@ -87,7 +117,16 @@ impl Board {
             }
         }
 
-        if !piece.is_pawn() {
+        if castling {
+            // In Chess960 king may land on the square of its rook, so both leave the board first
+            let rook = Piece::WhiteRook.to_color(self.active_color());
+            let rook_square = self.castle_settings().rook_square(target_square);
+            let rook_target = self.castle_settings().rook_target(target_square);
+            self.remove_piece(source_square, piece);
+            self.remove_piece(rook_square, rook);
+            self.set_piece(target_square, piece);
+            self.set_piece(rook_target, rook);
+        } else if !piece.is_pawn() {
             if piece == Piece::None {
                 self.display();
                 move_data.display();
@@ -114,32 +153,16 @@ impl Board {
             self.castle_settings_mut().set_by_square(source_square);
         }
 
-        if castling {
-            let rook = Piece::WhiteRook.to_color(self.active_color());
-            match target_square {
-                Square::G1 => self.move_piece(Square::H1, Square::F1, rook),
-                Square::C1 => self.move_piece(Square::A1, Square::D1, rook),
-                Square::G8 => self.move_piece(Square::H8, Square::F8, rook),
-                Square::C8 => self.move_piece(Square::A8, Square::D8, rook),
-                _ => panic!(
-                    "Target square of castle move does not match any possible castle squares."
-                ),
-            }
-        }
-
-        // If king is under attack after we made this move - move is not legal - unmake move
-        let is_legal = !self.is_king_in_check(mg);
+        // Hash in updated castle rights, en-passant square and side to move
+        self.game_state.zobrist_key ^= ZOBRIST.castling_key(self.castle_settings());
+        self.game_state.zobrist_key ^= ZOBRIST.en_passant_key(self.game_state.en_passant_target);
+        self.game_state.zobrist_key ^= ZOBRIST.side;
 
+        // Fullmove number is incremented after black's move
         self.game_state.active_color = self.opponent_color();
-        if self.active_color() == Color::Black {
-            self.game_state.fullmove_number += 1;
+        if self.active_color() == Color::White {
+            self.game_state.fullmove_number = self.game_state.fullmove_number.saturating_add(1);
         }
-
-        if !is_legal {
-            self.unmake_move();
-        }
-
-        is_legal
     }
 
     #[inline(always)]
@@ -147,6 +170,9 @@ impl Board {
         // Pop last made move from the history
         self.game_state = self.history.pop();
 
+        // Key is restored from history, piece manipulations below must not change it
+        let zobrist_key = self.game_state.zobrist_key;
+
         let move_data = self.game_state.next_move;
 
         let source_square = move_data.source_square();
@@ -158,7 +184,15 @@ impl Board {
         let castling = move_data.castling();
         let en_passant_square = Square::get_by_index(target_square as u8 ^ 8);
 
-        if promoted_piece.is_none() {
+        if castling {
+            let rook = Piece::WhiteRook.to_color(self.active_color());
+            let rook_square = self.castle_settings().rook_square(target_square);
+            let rook_target = self.castle_settings().rook_target(target_square);
+            self.remove_piece(target_square, piece);
+            self.remove_piece(rook_target, rook);
+            self.set_piece(source_square, piece);
+            self.set_piece(rook_square, rook);
+        } else if promoted_piece.is_none() {
             self.reverse_move(source_square, target_square, piece);
         } else {
             self.remove_piece(target_square, promoted_piece);
@@ -168,20 +202,6 @@ impl Board {
             );
         }
 
-        // King move is going to be unmade by 'reverse_move', but here we need to return rook to previous square
-        if castling {
-            let rook = Piece::WhiteRook.to_color(self.active_color());
-            match target_square {
-                Square::G1 => self.reverse_move(Square::H1, Square::F1, rook),
-                Square::C1 => self.reverse_move(Square::A1, Square::D1, rook),
-                Square::G8 => self.reverse_move(Square::H8, Square::F8, rook),
-                Square::C8 => self.reverse_move(Square::A8, Square::D8, rook),
-                _ => panic!(
-                    "Target square of castle move does not match any possible castle squares."
-                ),
-            };
-        }
-
         // Return captured piece back on board
         if !captured_piece.is_none() {
             self.set_piece(target_square, captured_piece);
@@ -194,5 +214,7 @@ impl Board {
                 Piece::WhitePawn.to_color(self.opponent_color()),
             );
         }
+
+        self.game_state.zobrist_key = zobrist_key;
     }
 }
@@ -1,10 +1,13 @@
 pub mod bit_board;
 pub mod board;
+pub mod epd;
 pub mod fen;
 pub mod game_state;
 pub mod history;
 pub mod make_move;
+pub mod packed;
 pub mod piece;
 pub mod square;
+pub mod zobrist;
 
 mod displays;
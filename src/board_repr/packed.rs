@@ -0,0 +1,325 @@
+/*
+ * Compact binary position encoding for datasets.
+ * Board is packed into 32 bytes:
+ *
+ * 0..8    occupancy, bit per square (A8 is bit 0), little-endian
+ * 8..24   4 bit piece codes of occupied squares in square order, low nibble first
+ * 24      castle rights (CastleAvailability::bits) and black to move flag (bit 4)
+ * 25      en-passant target square, 64 if there is none
+ * 26      halfmove clock
+ * 27      fullmove number, low byte
+ * 28      files of the castling rooks: king side in low nibble, queen side in high nibble
+ * 29      file of the castling king
+ * 30      fullmove number, high byte
+ * 31      reserved, zero
+ *
+ * Record files are a plain sequence of 36 byte records: packed board followed by an optional
+ * score (i16, little-endian, i16::MIN if missing), game result (0xff if missing) and a zero byte.
+ */
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+};
+
+use super::{
+    bit_board::BitBoard, board::Board, game_state::CastleAvailability, piece::Color, square::Square,
+};
+
+pub const PACKED_BOARD_SIZE: usize = 32;
+pub const PACKED_RECORD_SIZE: usize = PACKED_BOARD_SIZE + 4;
+
+const NO_EN_PASSANT: u8 = 64;
+const NO_SCORE: i16 = i16::MIN;
+const NO_RESULT: u8 = 0xff;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackedBoard(pub [u8; PACKED_BOARD_SIZE]);
+
+impl PackedBoard {
+    pub fn from_board(board: &Board) -> Self {
+        let mut bytes = [0u8; PACKED_BOARD_SIZE];
+        let mut occupancy = 0u64;
+        let mut nibble = 0;
+
+        for (square, piece) in board.piece_by_square.iter().enumerate() {
+            if piece.is_none() {
+                continue;
+            }
+            occupancy |= 1 << square;
+            bytes[8 + nibble / 2] |= (*piece as u8) << (4 * (nibble % 2));
+            nibble += 1;
+        }
+        bytes[0..8].copy_from_slice(&occupancy.to_le_bytes());
+
+        let state = &board.game_state;
+        let castle_settings = board.castle_settings();
+        bytes[24] = castle_settings.bits() | ((state.active_color == Color::Black) as u8) << 4;
+        bytes[25] = state.en_passant_target.map_or(NO_EN_PASSANT, |s| s as u8);
+        bytes[26] = state.halfmove_clock;
+        [bytes[27], bytes[30]] = state.fullmove_number.to_le_bytes();
+        bytes[28] = castle_settings.king_rook_file | castle_settings.queen_rook_file << 4;
+        bytes[29] = castle_settings.king_file;
+
+        Self(bytes)
+    }
+
+    pub fn to_board(&self) -> Result<Board, String> {
+        let bytes = &self.0;
+        let occupancy = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        if occupancy.count_ones() > 32 {
+            return Err(format!("Too many pieces in packed board: {occupancy:#x}"));
+        }
+
+        let mut bitboards = [BitBoard::default(); 12];
+        let squares = (0..64u8).filter(|s| occupancy & (1 << s) != 0);
+        for (nibble, square) in squares.enumerate() {
+            let code = (bytes[8 + nibble / 2] >> (4 * (nibble % 2))) & 0xf;
+            if code as usize >= bitboards.len() {
+                return Err(format!("Invalid piece code in packed board: {code}"));
+            }
+            bitboards[code as usize] |= Square::get_by_index(square).get_bitboard();
+        }
+
+        let flags = bytes[24];
+        let castle_settings = CastleAvailability {
+            can_white_castle_king: flags & 1 != 0,
+            can_white_castle_queen: flags & 2 != 0,
+            can_black_castle_king: flags & 4 != 0,
+            can_black_castle_queen: flags & 8 != 0,
+            king_file: bytes[29] & 7,
+            king_rook_file: bytes[28] & 7,
+            queen_rook_file: (bytes[28] >> 4) & 7,
+        };
+        let active_color = match flags & 0x10 {
+            0 => Color::White,
+            _ => Color::Black,
+        };
+        let en_passant_target = match bytes[25] {
+            square @ 0..=63 => Some(Square::get_by_index(square)),
+            _ => None,
+        };
+
+        Ok(Board::new(
+            bitboards,
+            active_color,
+            castle_settings,
+            en_passant_target,
+            bytes[26],
+            u16::from_le_bytes([bytes[27], bytes[30]]),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    BlackWin,
+    Draw,
+    WhiteWin,
+}
+
+impl GameResult {
+    // Points of the white side
+    pub fn white_score(&self) -> f64 {
+        match self {
+            GameResult::BlackWin => 0.0,
+            GameResult::Draw => 0.5,
+            GameResult::WhiteWin => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackedRecord {
+    pub board: PackedBoard,
+    // Centipawns from the side to move
+    pub score: Option<i16>,
+    pub result: Option<GameResult>,
+}
+
+impl PackedRecord {
+    pub fn new(board: &Board) -> Self {
+        Self {
+            board: PackedBoard::from_board(board),
+            score: None,
+            result: None,
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; PACKED_RECORD_SIZE] {
+        let mut bytes = [0u8; PACKED_RECORD_SIZE];
+        bytes[..PACKED_BOARD_SIZE].copy_from_slice(&self.board.0);
+        let score = self.score.unwrap_or(NO_SCORE);
+        bytes[PACKED_BOARD_SIZE..PACKED_BOARD_SIZE + 2].copy_from_slice(&score.to_le_bytes());
+        bytes[PACKED_BOARD_SIZE + 2] = self.result.map_or(NO_RESULT, |r| r as u8);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; PACKED_RECORD_SIZE]) -> Result<Self, String> {
+        let score = i16::from_le_bytes([bytes[PACKED_BOARD_SIZE], bytes[PACKED_BOARD_SIZE + 1]]);
+        let result = match bytes[PACKED_BOARD_SIZE + 2] {
+            0 => Some(GameResult::BlackWin),
+            1 => Some(GameResult::Draw),
+            2 => Some(GameResult::WhiteWin),
+            NO_RESULT => None,
+            code => return Err(format!("Invalid game result in packed record: {code}")),
+        };
+
+        Ok(Self {
+            board: PackedBoard(bytes[..PACKED_BOARD_SIZE].try_into().unwrap()),
+            score: Some(score).filter(|s| *s != NO_SCORE),
+            result,
+        })
+    }
+}
+
+pub struct PackedWriter<W: Write> {
+    inner: W,
+    pub records: u64,
+}
+
+impl PackedWriter<BufWriter<File>> {
+    pub fn create(path: &str) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write> PackedWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, records: 0 }
+    }
+
+    pub fn write(&mut self, record: &PackedRecord) -> io::Result<()> {
+        self.inner.write_all(&record.to_bytes())?;
+        self.records += 1;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+// Reads records one by one until the end of the stream
+pub struct PackedReader<R: Read> {
+    inner: R,
+}
+
+impl PackedReader<BufReader<File>> {
+    pub fn open(path: &str) -> io::Result<Self> {
+        Ok(Self::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: Read> PackedReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+}
+
+impl<R: Read> Iterator for PackedReader<R> {
+    type Item = io::Result<PackedRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut bytes = [0u8; PACKED_RECORD_SIZE];
+        let mut read = 0;
+        while read < PACKED_RECORD_SIZE {
+            match self.inner.read(&mut bytes[read..]) {
+                Ok(0) if read == 0 => return None,
+                Ok(0) => return Some(Err(io::ErrorKind::UnexpectedEof.into())),
+                Ok(n) => read += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        Some(
+            PackedRecord::from_bytes(&bytes)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        )
+    }
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board_repr::fen::Fen, _EMPTY, _EN_PASSANT, _POSITION_3, _POSITION_4, _POSITION_5,
+        _POSITION_6, _PROMOTION, _START_FEN, _TRICKY_POSITION,
+    };
+
+    use super::*;
+
+    const POSITIONS: [&str; 11] = [
+        _START_FEN,
+        _TRICKY_POSITION,
+        _POSITION_3,
+        _POSITION_4,
+        _POSITION_5,
+        _POSITION_6,
+        _EN_PASSANT,
+        _EMPTY,
+        _PROMOTION,
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9",
+        "rk2r2r/8/8/8/8/8/8/RK2R2R b EQeq - 99 200",
+    ];
+
+    #[test]
+    fn test_packed_board_round_trip() {
+        for fen in POSITIONS {
+            let board = Fen::to_board(fen);
+            let unpacked = PackedBoard::from_board(&board).to_board().unwrap();
+
+            assert_eq!(Fen::from_board(&unpacked), fen);
+            assert!(unpacked == board);
+            assert_eq!(unpacked.zobrist_key(), board.zobrist_key());
+        }
+
+        let late = Fen::to_board("4k3/8/8/8/8/8/8/4K3 b - - 12 300");
+        assert!(PackedBoard::from_board(&late).to_board().unwrap() == late);
+
+        let mut invalid = PackedBoard::from_board(&Fen::to_board(_START_FEN));
+        invalid.0[8] = 0xff;
+        assert!(invalid.to_board().is_err());
+    }
+
+    #[test]
+    fn test_record_stream_round_trip() {
+        let records: Vec<PackedRecord> = POSITIONS
+            .iter()
+            .enumerate()
+            .map(|(i, fen)| PackedRecord {
+                board: PackedBoard::from_board(&Fen::to_board(fen)),
+                score: (i % 2 == 0).then_some(i as i16 * -37),
+                result: [None, Some(GameResult::WhiteWin), Some(GameResult::Draw)][i % 3],
+            })
+            .collect();
+
+        let mut writer = PackedWriter::new(vec![]);
+        for record in &records {
+            writer.write(record).unwrap();
+        }
+        assert_eq!(writer.records, records.len() as u64);
+        let bytes = writer.into_inner();
+        assert_eq!(bytes.len(), records.len() * PACKED_RECORD_SIZE);
+
+        let read: Vec<PackedRecord> = PackedReader::new(bytes.as_slice())
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(read, records);
+        for (record, fen) in read.iter().zip(POSITIONS) {
+            assert_eq!(Fen::from_board(&record.board.to_board().unwrap()), fen);
+        }
+
+        // Truncated record at the end of the stream
+        let mut reader = PackedReader::new(&bytes[..PACKED_RECORD_SIZE + 5]);
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_err());
+    }
+}
@@ -1,5 +1,7 @@
 use strum_macros::{AsRefStr, Display, EnumIter, EnumString, FromRepr};
 
+use crate::evaluation::eval::{EVAL_PARAMS, PIECE_KINDS};
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Display, Default)]
 pub enum Color {
     #[default]
@@ -81,7 +83,7 @@ impl Piece {
             Self::BlackQueen => String::from("q"),
             Self::BlackKing => String::from("k"),
             Self::BlackBishop => String::from("b"),
-            Self::BlackKnight => String::from("k"),
+            Self::BlackKnight => String::from("n"),
             Self::BlackRook => String::from("r"),
 
             Self::None => String::new(),
@@ -168,23 +170,16 @@ impl Piece {
 }
 
 impl Piece {
+    // Material value from the evaluation parameters, negative for black pieces
     pub fn eval_value(&self) -> i16 {
-        match self {
-            Self::WhitePawn => 100,
-            Self::WhiteKnight => 300,
-            Self::WhiteBishop => 350,
-            Self::WhiteRook => 500,
-            Self::WhiteQueen => 1000,
-            Self::WhiteKing => 10000,
-
-            Self::BlackPawn => -100,
-            Self::BlackKnight => -300,
-            Self::BlackBishop => -350,
-            Self::BlackRook => -500,
-            Self::BlackQueen => -1000,
-            Self::BlackKing => -10000,
-
-            _ => 0,
+        if self.is_none() {
+            return 0;
+        }
+
+        let value = EVAL_PARAMS.material[*self as usize % PIECE_KINDS];
+        match self.color() {
+            Color::White => value,
+            _ => -value,
         }
     }
 }
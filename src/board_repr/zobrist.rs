@@ -0,0 +1,142 @@
+/*
+ * Zobrist hashing.
+ * Every (piece, square) pair, side to move, castling rights combination and en-passant file
+ * gets a random 64 bit key. Position key is XOR of all keys that describe the position,
+ * which allows us to update it incrementally while making and unmaking moves.
+ *
+ * Keys are generated at compile time with a fixed seed, so they are the same on every run.
+ */
+
+use super::{board::Board, game_state::CastleAvailability, piece::Color, square::Square};
+
+pub struct Zobrist {
+    pub pieces: [[u64; 64]; 12],
+    pub castling: [u64; 16],
+    pub en_passant: [u64; 8],
+    pub side: u64,
+}
+
+pub const ZOBRIST: Zobrist = Zobrist::new();
+
+// https://prng.di.unimi.it/splitmix64.c
+const fn split_mix(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    (state, z ^ (z >> 31))
+}
+
+impl Zobrist {
+    const fn new() -> Self {
+        let mut state = 0x1a2b3c4d5e6f7081u64;
+        let mut pieces = [[0u64; 64]; 12];
+        let mut castling = [0u64; 16];
+        let mut en_passant = [0u64; 8];
+
+        let mut piece = 0;
+        while piece < 12 {
+            let mut square = 0;
+            while square < 64 {
+                let (next_state, key) = split_mix(state);
+                state = next_state;
+                pieces[piece][square] = key;
+                square += 1;
+            }
+            piece += 1;
+        }
+
+        let mut i = 0;
+        while i < 16 {
+            let (next_state, key) = split_mix(state);
+            state = next_state;
+            castling[i] = key;
+            i += 1;
+        }
+
+        let mut i = 0;
+        while i < 8 {
+            let (next_state, key) = split_mix(state);
+            state = next_state;
+            en_passant[i] = key;
+            i += 1;
+        }
+
+        let (_, side) = split_mix(state);
+
+        Self {
+            pieces,
+            castling,
+            en_passant,
+            side,
+        }
+    }
+
+    pub fn piece_key(&self, piece_index: usize, square: Square) -> u64 {
+        self.pieces[piece_index][square as usize]
+    }
+
+    pub fn castling_key(&self, castle_settings: &CastleAvailability) -> u64 {
+        self.castling[castle_settings.bits() as usize]
+    }
+
+    pub fn en_passant_key(&self, en_passant_target: Option<Square>) -> u64 {
+        match en_passant_target {
+            Some(square) => self.en_passant[square.file() as usize],
+            None => 0,
+        }
+    }
+
+    // Computes key of the position from scratch.
+    pub fn hash(&self, board: &Board) -> u64 {
+        let mut key = 0u64;
+
+        for (square_index, piece) in board.piece_by_square.iter().enumerate() {
+            if piece.is_none() {
+                continue;
+            }
+            key ^= self.piece_key(*piece as usize, Square::get_by_index(square_index as u8));
+        }
+
+        key ^= self.castling_key(board.castle_settings());
+        key ^= self.en_passant_key(board.game_state.en_passant_target);
+
+        if board.active_color() == Color::Black {
+            key ^= self.side;
+        }
+
+        key
+    }
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board_repr::fen::Fen,
+        move_generation::{generator::MoveGenerator, move_list::MoveList, moves::MoveType},
+        _TRICKY_POSITION,
+    };
+
+    use super::ZOBRIST;
+
+    #[test]
+    fn test_incremental_key_matches_full_hash() {
+        let mg = MoveGenerator::shared();
+        let mut board = Fen::to_board(_TRICKY_POSITION);
+        let initial_key = board.zobrist_key();
+
+        let mut move_list = MoveList::new();
+        mg.generate_moves(&board, &mut move_list, MoveType::All);
+
+        for move_data in move_list {
+            if board.make_move(move_data, mg) {
+                assert_eq!(board.zobrist_key(), ZOBRIST.hash(&board));
+                board.unmake_move();
+            }
+            assert_eq!(board.zobrist_key(), initial_key);
+        }
+    }
+}
@@ -0,0 +1,308 @@
+/*
+ * Polyglot book builder.
+ * Replays PGN games up to a maximum ply and counts wins, draws and losses of every move played
+ * in every position, from the side of the player who made the move. Moves played in too few
+ * games or scoring too low are dropped, the rest are written with the Polyglot weight of
+ * 2 per win and 1 per draw:
+ *
+ * chess_engine_rust makebook GAMES.pgn [--out book.bin] [--max-ply N] [--min-games N] [--min-score PERCENT]
+ */
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{BufRead, BufReader},
+};
+
+use crate::{
+    board_repr::piece::Color,
+    move_generation::generator::MoveGenerator,
+    utils::pgn::{Pgn, PgnGame, PgnReader},
+};
+
+use super::polyglot::{polyglot_key, BookEntry, PolyglotBook};
+
+#[derive(Debug, Clone, Copy)]
+pub struct BookBuilderOptions {
+    pub max_ply: usize,
+    pub min_games: u32,
+    // Percentage of points the move has to score, 0..=100
+    pub min_score: f64,
+}
+
+impl Default for BookBuilderOptions {
+    fn default() -> Self {
+        Self {
+            max_ply: 30,
+            min_games: 1,
+            min_score: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MoveStats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MoveStats {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    // Percentage of points scored with the move
+    pub fn score(&self) -> f64 {
+        match self.games() {
+            0 => 0.0,
+            games => (self.wins as f64 + self.draws as f64 / 2.0) * 100.0 / games as f64,
+        }
+    }
+
+    pub fn weight(&self) -> u32 {
+        self.wins * 2 + self.draws
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BookBuilder {
+    pub options: BookBuilderOptions,
+    // Move statistics by Polyglot key and Polyglot move
+    pub positions: BTreeMap<u64, BTreeMap<u16, MoveStats>>,
+    pub games: u64,
+    // Games without a decisive or drawn result
+    pub skipped_games: u64,
+    // Games that could not be parsed
+    pub failed_games: u64,
+}
+
+impl BookBuilder {
+    pub fn new(options: BookBuilderOptions) -> Self {
+        Self {
+            options,
+            ..Default::default()
+        }
+    }
+
+    pub fn add_game(&mut self, game: &PgnGame, mg: &MoveGenerator) {
+        let Ok(mut board) = game.start_board() else {
+            self.failed_games += 1;
+            return;
+        };
+        let winner = match game.result.as_str() {
+            "1-0" => Some(Color::White),
+            "0-1" => Some(Color::Black),
+            "1/2-1/2" => None,
+            _ => {
+                self.skipped_games += 1;
+                return;
+            }
+        };
+        self.games += 1;
+
+        for move_data in game.mainline().into_iter().take(self.options.max_ply) {
+            let key = polyglot_key(&board);
+            let mover = board.active_color();
+            if !board.make_move(move_data, mg) {
+                break;
+            }
+
+            let stats = self
+                .positions
+                .entry(key)
+                .or_default()
+                .entry(BookEntry::encode_move(move_data))
+                .or_default();
+            match winner {
+                Some(color) if color == mover => stats.wins += 1,
+                Some(_) => stats.losses += 1,
+                None => stats.draws += 1,
+            }
+        }
+    }
+
+    // Games are read one by one, a game with illegal moves or broken syntax is counted and skipped
+    pub fn add_pgn(&mut self, reader: impl BufRead, mg: &MoveGenerator) -> Result<(), String> {
+        for text in PgnReader::new(reader) {
+            let text = text.map_err(|e| e.to_string())?;
+            match Pgn::parse(&text, mg) {
+                Ok(games) => games.iter().for_each(|game| self.add_game(game, mg)),
+                Err(_) => self.failed_games += 1,
+            }
+        }
+        Ok(())
+    }
+
+    // Filtered entries sorted by key, heaviest move of a position first
+    pub fn build(&self) -> PolyglotBook {
+        let mut entries = vec![];
+
+        for (key, moves) in &self.positions {
+            let mut position_entries: Vec<(u16, u32)> = moves
+                .iter()
+                .filter(|(_, stats)| {
+                    stats.games() >= self.options.min_games
+                        && stats.score() >= self.options.min_score
+                        && stats.weight() > 0
+                })
+                .map(|(raw_move, stats)| (*raw_move, stats.weight()))
+                .collect();
+            position_entries.sort_by_key(|(_, weight)| std::cmp::Reverse(*weight));
+
+            // Weights are scaled down together when the heaviest one does not fit in 16 bits
+            let max_weight = position_entries.first().map_or(0, |(_, weight)| *weight);
+            let scale = (max_weight as f64 / u16::MAX as f64).max(1.0);
+
+            for (raw_move, weight) in position_entries {
+                entries.push(BookEntry {
+                    key: *key,
+                    raw_move,
+                    weight: ((weight as f64 / scale) as u16).max(1),
+                    learn: 0,
+                });
+            }
+        }
+
+        PolyglotBook { entries }
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<(String, BookBuilderOptions, String), String> {
+    let mut options = BookBuilderOptions::default();
+    let mut output = String::from("book.bin");
+    let mut args = args.iter();
+    let input = args.next().ok_or("Missing PGN file")?.clone();
+
+    while let Some(name) = args.next() {
+        let value = args.next().ok_or(format!("Missing value for {name}"))?;
+        let invalid = || format!("Invalid value for {name}: {value}");
+
+        match name.as_str() {
+            "--out" => output = value.clone(),
+            "--max-ply" => options.max_ply = value.parse().map_err(|_| invalid())?,
+            "--min-games" => options.min_games = value.parse().map_err(|_| invalid())?,
+            "--min-score" => options.min_score = value.parse().map_err(|_| invalid())?,
+            _ => return Err(format!("Unknown argument {name}")),
+        }
+    }
+
+    Ok((input, options, output))
+}
+
+// Command line entry: "chess_engine_rust makebook GAMES.pgn [--out FILE] [--max-ply N] [--min-games N] [--min-score PERCENT]"
+pub fn run_book_builder(args: &[String]) {
+    let (input, options, output) = match parse_options(args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+
+    let mg = MoveGenerator::shared();
+    let mut builder = BookBuilder::new(options);
+    let added = File::open(&input)
+        .map_err(|e| e.to_string())
+        .and_then(|file| builder.add_pgn(BufReader::new(file), mg));
+    if let Err(e) = added {
+        eprintln!("Can not read games from {input}: {e}");
+        return;
+    }
+
+    let book = builder.build();
+    let moves: usize = builder.positions.values().map(|moves| moves.len()).sum();
+    eprintln!(
+        "Games: {} ({} skipped without result, {} failed to parse)",
+        builder.games, builder.skipped_games, builder.failed_games
+    );
+    eprintln!(
+        "Positions: {}, moves: {}, book entries: {}",
+        builder.positions.len(),
+        moves,
+        book.entries.len()
+    );
+
+    match fs::write(&output, book.to_bytes()) {
+        Ok(()) => eprintln!("Written to {output}"),
+        Err(e) => eprintln!("Can not write {output}: {e}"),
+    }
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use crate::{board_repr::fen::Fen, _START_FEN};
+
+    use super::*;
+
+    const GAMES: &str = r#"
+[Result "1-0"]
+1. e4 e5 2. Nf3 Nc6 1-0
+
+[Result "0-1"]
+1. e4 c5 2. Nf3 0-1
+
+[Result "1/2-1/2"]
+1. d4 d5 1/2-1/2
+
+[Result "*"]
+1. c4 *
+
+[Result "1-0"]
+1. e4 e4 1-0
+
+[Result "1-0"]
+[FEN "bogus"]
+1. e4 1-0
+"#;
+
+    #[test]
+    fn test_build_book_from_pgn() {
+        let mg = MoveGenerator::shared();
+        let mut builder = BookBuilder::new(BookBuilderOptions {
+            max_ply: 2,
+            ..Default::default()
+        });
+        builder.add_pgn(GAMES.as_bytes(), mg).unwrap();
+        assert_eq!(
+            (builder.games, builder.skipped_games, builder.failed_games),
+            (3, 1, 2)
+        );
+
+        let start = Fen::to_board(_START_FEN);
+        let stats: Vec<MoveStats> = builder.positions[&polyglot_key(&start)]
+            .values()
+            .copied()
+            .collect();
+        assert_eq!(stats.len(), 2);
+        assert!(stats.contains(&MoveStats {
+            wins: 1,
+            draws: 0,
+            losses: 1
+        }));
+
+        // e4 scores 2, d4 scores 1, Nf3 is beyond max ply
+        let book = PolyglotBook::from_bytes(&builder.build().to_bytes()).unwrap();
+        let moves: Vec<(String, u16)> = book
+            .moves(&start, mg)
+            .iter()
+            .map(|(m, weight)| (m.to_uci_string(), *weight))
+            .collect();
+        assert_eq!(
+            moves,
+            [(String::from("e2e4"), 2), (String::from("d2d4"), 1)]
+        );
+        assert!(book.entries.windows(2).all(|w| w[0].key <= w[1].key));
+        // e5 lost its only game and is left out
+        assert_eq!(book.entries.len(), 4);
+
+        builder.options.min_games = 2;
+        assert_eq!(builder.build().entries.len(), 1);
+        builder.options.min_games = 1;
+        builder.options.min_score = 60.0;
+        assert_eq!(builder.build().entries.len(), 1);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod builder;
+pub mod polyglot;
+#[rustfmt::skip]
+pub mod polyglot_randoms;
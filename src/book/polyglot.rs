@@ -0,0 +1,410 @@
+/*
+ * Polyglot opening book.
+ * Book file is a sorted array of 16 byte big-endian entries: position key, move, weight and
+ * learn data. All entries of a position are next to each other, so they are found with a
+ * binary search on the key.
+ *
+ * Moves are packed into 16 bits: target file and row, source file and row (3 bits each, rows
+ * counted from rank 1) and promotion piece. Castling is stored as the king taking its own rook.
+ */
+
+use std::{fs, io};
+
+use rand::Rng;
+
+use crate::{
+    board_repr::{
+        board::Board,
+        piece::{Color, Piece},
+        square::Square,
+    },
+    move_generation::{
+        generator::MoveGenerator, move_list::MoveList, moves::Move, static_tables::PAWN_ATTACKS,
+    },
+};
+
+use super::polyglot_randoms::POLYGLOT_RANDOMS;
+
+pub const BOOK_ENTRY_SIZE: usize = 16;
+pub const DEFAULT_BOOK_DEPTH: u16 = 20;
+
+const RANDOM_CASTLE: usize = 768;
+const RANDOM_EN_PASSANT: usize = 772;
+const RANDOM_TURN: usize = 780;
+
+// Polyglot square index, counted from a1
+fn polyglot_square(square: Square) -> usize {
+    (7 - square.rank() as usize) * 8 + square.file() as usize
+}
+
+// Polyglot piece kinds are bp wp bn wn bb wb br wr bq wq bk wk
+fn polyglot_kind(piece: Piece) -> usize {
+    let role = match piece {
+        Piece::WhitePawn | Piece::BlackPawn => 0,
+        Piece::WhiteKnight | Piece::BlackKnight => 1,
+        Piece::WhiteBishop | Piece::BlackBishop => 2,
+        Piece::WhiteRook | Piece::BlackRook => 3,
+        Piece::WhiteQueen | Piece::BlackQueen => 4,
+        _ => 5,
+    };
+
+    role * 2 + (piece.color() == Color::White) as usize
+}
+
+// Polyglot key of the position, unrelated to the engine zobrist key
+pub fn polyglot_key(board: &Board) -> u64 {
+    let mut key = 0u64;
+
+    for (square_index, piece) in board.piece_by_square.iter().enumerate() {
+        if piece.is_none() {
+            continue;
+        }
+        let square = Square::get_by_index(square_index as u8);
+        key ^= POLYGLOT_RANDOMS[64 * polyglot_kind(*piece) + polyglot_square(square)];
+    }
+
+    let castle_settings = board.castle_settings();
+    for (i, available) in [
+        castle_settings.can_white_castle_king,
+        castle_settings.can_white_castle_queen,
+        castle_settings.can_black_castle_king,
+        castle_settings.can_black_castle_queen,
+    ]
+    .iter()
+    .enumerate()
+    {
+        if *available {
+            key ^= POLYGLOT_RANDOMS[RANDOM_CASTLE + i];
+        }
+    }
+
+    // En-passant file counts only if a pawn of the side to move stands next to the pushed pawn
+    if let Some(square) = board.game_state.en_passant_target {
+        let pawns = board.bitboards[Piece::WhitePawn.to_color(board.active_color()) as usize];
+        let attackers = PAWN_ATTACKS[board.opponent_color() as usize][square as usize];
+        if !(pawns & attackers).empty() {
+            key ^= POLYGLOT_RANDOMS[RANDOM_EN_PASSANT + square.file() as usize];
+        }
+    }
+
+    if board.active_color() == Color::White {
+        key ^= POLYGLOT_RANDOMS[RANDOM_TURN];
+    }
+
+    key
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    pub fn from_bytes(bytes: &[u8; BOOK_ENTRY_SIZE]) -> Self {
+        Self {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            raw_move: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; BOOK_ENTRY_SIZE] {
+        let mut bytes = [0u8; BOOK_ENTRY_SIZE];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.raw_move.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+
+    // Finds the legal move the entry stands for, king-takes-rook is turned into castling
+    pub fn decode_move(&self, board: &Board, mg: &MoveGenerator) -> Option<Move> {
+        let square = |file: u16, row: u16| Square::from_file_and_rank(file as u8, 7 - row as u8);
+        let source_square = square((self.raw_move >> 6) & 7, (self.raw_move >> 9) & 7);
+        let mut target_square = square(self.raw_move & 7, (self.raw_move >> 3) & 7);
+        let promoted_piece = match (self.raw_move >> 12) & 7 {
+            1 => Piece::WhiteKnight,
+            2 => Piece::WhiteBishop,
+            3 => Piece::WhiteRook,
+            4 => Piece::WhiteQueen,
+            _ => Piece::None,
+        };
+
+        let moving_piece = board.piece_by_square[source_square as usize];
+        let target_piece = board.piece_by_square[target_square as usize];
+        if moving_piece.is_king() && target_piece == Piece::WhiteRook.to_color(moving_piece.color())
+        {
+            let king_side = target_square.file() > source_square.file();
+            target_square =
+                Square::from_file_and_rank(if king_side { 6 } else { 2 }, source_square.rank());
+        }
+
+        let mut legal_moves = MoveList::new();
+        mg.generate_legal_moves(board, &mut legal_moves);
+        legal_moves.into_iter().find(|m| {
+            m.source_square() == source_square
+                && m.target_square() == target_square
+                && m.promoted_piece().to_color(Color::White) == promoted_piece
+        })
+    }
+
+    pub fn encode_move(move_data: Move) -> u16 {
+        let source_square = move_data.source_square();
+        let mut target_square = move_data.target_square();
+
+        // King takes its own rook
+        if move_data.castling() {
+            let rook_file = if target_square.file() == 6 { 7 } else { 0 };
+            target_square = Square::from_file_and_rank(rook_file, target_square.rank());
+        }
+
+        let promotion = match move_data.promoted_piece().to_color(Color::White) {
+            Piece::WhiteKnight => 1,
+            Piece::WhiteBishop => 2,
+            Piece::WhiteRook => 3,
+            Piece::WhiteQueen => 4,
+            _ => 0,
+        };
+
+        let target = polyglot_square(target_square) as u16;
+        let source = polyglot_square(source_square) as u16;
+        target | source << 6 | promotion << 12
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookSelection {
+    // Move with the highest weight
+    Best,
+    // Random move, chance is proportional to the weight
+    Weighted,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PolyglotBook {
+    // Sorted by key, as in the file
+    pub entries: Vec<BookEntry>,
+}
+
+impl PolyglotBook {
+    pub fn load(path: &str) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        if !bytes.len().is_multiple_of(BOOK_ENTRY_SIZE) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Book size is not a multiple of the entry size",
+            ));
+        }
+
+        let entries = bytes
+            .chunks_exact(BOOK_ENTRY_SIZE)
+            .map(|chunk| BookEntry::from_bytes(chunk.try_into().unwrap()))
+            .collect();
+
+        Ok(Self { entries })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.entries.iter().flat_map(|e| e.to_bytes()).collect()
+    }
+
+    pub fn entries_for(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|e| e.key < key);
+        let end = start + self.entries[start..].partition_point(|e| e.key == key);
+        &self.entries[start..end]
+    }
+
+    // Legal book moves of the position with their weights
+    pub fn moves(&self, board: &Board, mg: &MoveGenerator) -> Vec<(Move, u16)> {
+        self.entries_for(polyglot_key(board))
+            .iter()
+            .filter_map(|entry| Some((entry.decode_move(board, mg)?, entry.weight)))
+            .collect()
+    }
+
+    pub fn pick_move(
+        &self,
+        board: &Board,
+        mg: &MoveGenerator,
+        selection: BookSelection,
+        rng: &mut impl Rng,
+    ) -> Option<Move> {
+        let moves = self.moves(board, mg);
+
+        match selection {
+            BookSelection::Best => moves
+                .iter()
+                .max_by_key(|(_, weight)| *weight)
+                .map(|(move_data, _)| *move_data),
+            BookSelection::Weighted => {
+                let total: u32 = moves.iter().map(|(_, weight)| *weight as u32).sum();
+                if total == 0 {
+                    return moves.first().map(|(move_data, _)| *move_data);
+                }
+
+                let mut pick = rng.gen_range(0..total);
+                for (move_data, weight) in moves {
+                    if pick < weight as u32 {
+                        return Some(move_data);
+                    }
+                    pick -= weight as u32;
+                }
+                None
+            }
+        }
+    }
+}
+
+/*
+ * Book settings controlled by UCI options 'OwnBook', 'BookFile', 'BookDepth' and 'BookBestMove'.
+ * Book is only probed while the game is at most 'depth' full moves long.
+ */
+#[derive(Debug, Clone)]
+pub struct OpeningBook {
+    pub enabled: bool,
+    pub depth: u16,
+    pub selection: BookSelection,
+    pub book: Option<PolyglotBook>,
+}
+
+impl Default for OpeningBook {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            depth: DEFAULT_BOOK_DEPTH,
+            selection: BookSelection::Weighted,
+            book: None,
+        }
+    }
+}
+
+impl OpeningBook {
+    // Empty path unloads the book
+    pub fn set_file(&mut self, path: &str) -> io::Result<()> {
+        self.book = None;
+        if !path.is_empty() {
+            self.book = Some(PolyglotBook::load(path)?);
+        }
+        Ok(())
+    }
+
+    pub fn probe(&self, board: &Board, mg: &MoveGenerator) -> Option<Move> {
+        let book = self.book.as_ref().filter(|_| self.enabled)?;
+        if board.game_state.fullmove_number > self.depth {
+            return None;
+        }
+
+        book.pick_move(board, mg, self.selection, &mut rand::thread_rng())
+    }
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    use crate::{board_repr::fen::Fen, comm::uci::UCI, _START_FEN};
+
+    use super::*;
+
+    #[test]
+    fn test_polyglot_keys_match_reference() {
+        // Reference keys from the Polyglot book format specification
+        for (fen, key) in [
+            (_START_FEN, 0x463b96181691fc9c),
+            (
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                0x823c9b50fd114196,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+                0x0756b94461c50fb0,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2",
+                0x662fafb965db29d4,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                0x22a48b5a8e47ff78,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3",
+                0x652a607ca3f242c1,
+            ),
+            (
+                "rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4",
+                0x00fdd303c946bdd9,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3",
+                0x3c8123ea7b067637,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4",
+                0x5c3f9b829b279560,
+            ),
+        ] {
+            assert_eq!(polyglot_key(&Fen::to_board(fen)), key, "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_book_probe_and_castling_decode() {
+        let mg = MoveGenerator::shared();
+        let start = Fen::to_board(_START_FEN);
+        let castling = Fen::to_board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        let uci = |board: &Board, m: &str| UCI::parse_move(m, board, mg).unwrap();
+
+        let mut entries = vec![];
+        for (board, m, weight) in [
+            (&start, "e2e4", 30),
+            (&start, "d2d4", 10),
+            (&start, "g1f3", 0),
+            (&castling, "e1g1", 5),
+            (&castling, "e1c1", 1),
+        ] {
+            entries.push(BookEntry {
+                key: polyglot_key(board),
+                raw_move: BookEntry::encode_move(uci(board, m)),
+                weight,
+                learn: 0,
+            });
+        }
+        entries.sort_by_key(|e| e.key);
+        let book = PolyglotBook::from_bytes(&PolyglotBook { entries }.to_bytes()).unwrap();
+
+        // King takes rook is stored and read back as castling
+        assert_eq!(
+            book.entries_for(polyglot_key(&castling))[0].raw_move & 0x3f,
+            7
+        );
+        let moves: Vec<String> = book
+            .moves(&castling, mg)
+            .iter()
+            .map(|(m, _)| m.to_uci_string())
+            .collect();
+        assert_eq!(moves, ["e1g1", "e1c1"]);
+
+        let mut rng = ChaChaRng::seed_from_u64(1);
+        let best = book.pick_move(&start, mg, BookSelection::Best, &mut rng);
+        assert_eq!(best.unwrap().to_uci_string(), "e2e4");
+        for _ in 0..20 {
+            let picked = book.pick_move(&start, mg, BookSelection::Weighted, &mut rng);
+            assert_ne!(picked.unwrap().to_uci_string(), "g1f3");
+        }
+        assert!(book
+            .moves(&Fen::to_board("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), mg)
+            .is_empty());
+    }
+}
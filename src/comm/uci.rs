@@ -1,44 +1,117 @@
 use std::{
     io::{stdin, stdout, Write},
     str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
 };
 
 use crate::{
-    board_repr::{board::Board, fen::Fen, piece::Piece, square::Square},
+    board_repr::{
+        board::Board,
+        fen::Fen,
+        game_state::CastleAvailability,
+        piece::{Color, Piece},
+        square::Square,
+    },
+    book::polyglot::{BookSelection, OpeningBook, DEFAULT_BOOK_DEPTH},
+    evaluation::eval::{set_active_params, EvalParams, EVAL_PARAMS},
     move_generation::{
         generator::MoveGenerator,
         move_list::MoveList,
         moves::{Move, MoveType},
     },
-    search::negamax::Search,
-    utils::traits::DisplayExtension,
+    search::{
+        negamax::MATE_SCORE,
+        negamax::MATE_VALUE,
+        smp::{lazy_smp_with_abort, scaling_benchmark, SearchLimits, SearchResult, MAX_THREADS},
+        transposition::{TranspositionTable, DEFAULT_HASH_SIZE_MB, MAX_HASH_SIZE_MB},
+    },
+    utils::{
+        bench::{bench, BENCH_POSITIONS, DEFAULT_BENCH_DEPTH},
+        perft::{perft_test, PerftTable},
+        perft_suite::{
+            chess960_perft_suite, parse_perft_suite, run_perft_suite, standard_perft_suite,
+        },
+        test_suite::{parse_test_suite, run_test_suite, DEFAULT_TEST_SUITE_MOVETIME},
+        traits::DisplayExtension,
+    },
     _START_FEN,
 };
 
+// Depth used by 'go' when no limits were given
+const DEFAULT_DEPTH: i8 = 6;
+
 pub struct UCI;
 
+// Search running in the background while the loop keeps reading commands
+pub struct SearchThread {
+    handle: JoinHandle<()>,
+    abort: Arc<AtomicBool>,
+}
+
+impl SearchThread {
+    // Ends the search early, it still prints 'bestmove' before finishing
+    pub fn stop(self) {
+        self.abort.store(true, Ordering::Relaxed);
+        self.handle.join().expect("Search thread panicked");
+    }
+}
+
 impl UCI {
-    pub fn uci_loop() {
+    // 'first_command' is the line that was already read while detecting the protocol
+    pub fn uci_loop(first_command: Option<String>) {
         let mut board = Fen::to_board(_START_FEN);
-        let mg = MoveGenerator::new();
-
-        UCI::id();
-        UCI::uciok();
+        let mg = MoveGenerator::shared();
+        let mut tt = Arc::new(TranspositionTable::default());
+        let mut threads = 1usize;
+        let mut book = OpeningBook::default();
+        let mut chess960 = false;
+        let mut pending_command = first_command;
+        let mut search: Option<SearchThread> = None;
+        // Allocated on the first perft with the 'Hash' size, counts stay valid between runs
+        let mut perft_table: Option<PerftTable> = None;
 
         loop {
             let _ = stdout().flush();
-            let mut command = String::new();
-            stdin()
-                .read_line(&mut command)
-                .expect("Can't take user input");
+            let mut command = match pending_command.take() {
+                Some(command) => command,
+                None => {
+                    let mut command = String::new();
+                    let read = stdin()
+                        .read_line(&mut command)
+                        .expect("Can't take user input");
+
+                    // End of input, GUI is gone
+                    if read == 0 {
+                        if let Some(search) = search.take() {
+                            search.stop();
+                        }
+                        break;
+                    }
+                    command
+                }
+            };
             command = command.trim().to_string();
 
-            if command == "\n" {
+            if command.is_empty() {
                 continue;
             }
 
             if command == "isready" {
                 UCI::readyok();
+                continue;
+            }
+
+            // Any other command ends the running search, 'stop' and 'ponderhit' only do that
+            if let Some(search) = search.take() {
+                search.stop();
+            }
+            if command == "stop" || command == "ponderhit" {
+                continue;
             }
 
             if command == "uci" {
@@ -47,11 +120,31 @@ impl UCI {
             }
 
             if command == "ucinewgame" {
-                UCI::parse_position("position startpos", &mut board, &mg);
+                UCI::parse_position("position startpos", &mut board, mg);
+                tt.clear();
+            }
+
+            if command.starts_with("setoption") {
+                let tt = Arc::get_mut(&mut tt).expect("Search thread still holds the hash table");
+                UCI::parse_setoption(&command, tt, &mut threads, &mut book, &mut chess960);
+                if command.to_lowercase().contains("name hash") {
+                    perft_table = None;
+                }
+                continue;
+            }
+
+            if command.starts_with("bench") {
+                UCI::parse_bench(&command, mg);
+                continue;
+            }
+
+            if command.starts_with("scaling") {
+                UCI::parse_scaling(&command, mg, threads);
+                continue;
             }
 
             if command.starts_with("position") {
-                UCI::parse_position(&command, &mut board, &mg);
+                UCI::parse_position(&command, &mut board, mg);
                 continue;
             }
 
@@ -60,8 +153,24 @@ impl UCI {
                 continue;
             }
 
+            if command.starts_with("perftsuite") {
+                UCI::parse_perft_suite(&command, mg);
+                continue;
+            }
+
+            if command.starts_with("testsuite") {
+                UCI::parse_test_suite(&command, mg, &tt, threads);
+                continue;
+            }
+
+            if command.starts_with("go perft") || command.starts_with("divide") {
+                let table = perft_table.get_or_insert_with(|| PerftTable::new(tt.size_mb()));
+                UCI::parse_perft(&command, &board, mg, threads, table);
+                continue;
+            }
+
             if command.starts_with("go") {
-                UCI::parse_go(&command, &mut board, &mg);
+                search = UCI::parse_go(&command, &board, mg, &tt, threads, &book, chess960);
                 continue;
             }
 
@@ -72,26 +181,67 @@ impl UCI {
     }
 
     pub fn parse_move(move_str: &str, board: &Board, mg: &MoveGenerator) -> Option<Move> {
+        if move_str.len() < 4 || !move_str.is_ascii() {
+            return None;
+        }
+
         let (source_square_str, parts) = move_str.split_at(2);
         let (target_square_str, promoted) = parts.split_at(2);
 
         let Ok(source_square) = Square::from_str(&source_square_str.to_uppercase()) else {
             return None;
         };
-        let Ok(target_square) = Square::from_str(&target_square_str.to_uppercase()) else {
+        let Ok(mut target_square) = Square::from_str(&target_square_str.to_uppercase()) else {
             return None;
         };
         let promoted_piece = Piece::from_str(promoted).unwrap_or(Piece::None);
 
+        // King takes own rook is castling (UCI_Chess960), king goes to the 'g' or 'c' file
+        let color = board.active_color();
+        let king_takes_rook = board.piece_by_square[source_square as usize]
+            == Piece::WhiteKing.to_color(color)
+            && board.piece_by_square[target_square as usize] == Piece::WhiteRook.to_color(color);
+        if king_takes_rook {
+            let file = if target_square.file() > source_square.file() {
+                6
+            } else {
+                2
+            };
+            target_square = Square::from_file_and_rank(file, source_square.rank());
+        }
+
         let mut move_list = MoveList::new();
         mg.generate_moves(board, &mut move_list, MoveType::All);
 
-        move_list.moves.into_iter().find(|x| {
-            x.source_square() == source_square
-                && x.target_square() == target_square
-                && (x.promoted_piece() == promoted_piece
-                    || x.promoted_piece() == promoted_piece.opposite_color())
-        })
+        // In Chess960 a king move may share squares with castling, plain move is meant then
+        move_list.moves[..move_list.count as usize]
+            .iter()
+            .filter(|x| {
+                x.source_square() == source_square
+                    && x.target_square() == target_square
+                    && (x.promoted_piece() == promoted_piece
+                        || x.promoted_piece() == promoted_piece.opposite_color())
+            })
+            .min_by_key(|x| x.castling() != king_takes_rook)
+            .copied()
+    }
+
+    // With UCI_Chess960 castling is written as king takes rook
+    pub fn move_string(
+        move_data: Move,
+        castle_settings: &CastleAvailability,
+        chess960: bool,
+    ) -> String {
+        if chess960 && move_data.castling() {
+            let rook_square = castle_settings.rook_square(move_data.target_square());
+            return format!(
+                "{}{}",
+                move_data.source_square().to_string().to_lowercase(),
+                rook_square.to_string().to_lowercase()
+            );
+        }
+
+        move_data.to_uci_string()
     }
 
     pub fn parse_position(command: &str, board: &mut Board, mg: &MoveGenerator) {
@@ -142,30 +292,311 @@ impl UCI {
         }
     }
 
-    pub fn parse_go(command: &str, board: &mut Board, mg: &MoveGenerator) {
+    /*
+     * Starts the search on its own thread, so that 'stop' and 'quit' can be read meanwhile.
+     * 'go infinite' and 'go ponder' search without limits and print 'bestmove' only after
+     * 'stop' (or 'ponderhit', which ends pondering with the move found so far).
+     */
+    pub fn parse_go(
+        command: &str,
+        board: &Board,
+        mg: &'static MoveGenerator,
+        tt: &Arc<TranspositionTable>,
+        threads: usize,
+        book: &OpeningBook,
+        chess960: bool,
+    ) -> Option<SearchThread> {
+        // Rook files do not change during the game, so root settings serve the whole search
+        let castle_settings = *board.castle_settings();
+
+        if let Some(move_data) = book.probe(board, mg) {
+            UCI::bestmove(&UCI::move_string(move_data, &castle_settings, chess960));
+            return None;
+        }
+
         let parts: Vec<&str> = command.split_whitespace().collect();
-        let mut depth = 0;
+        let infinite = parts.contains(&"infinite") || parts.contains(&"ponder");
+        let limits = match infinite {
+            true => SearchLimits::default(),
+            false => UCI::parse_limits(command, board.active_color()),
+        };
 
-        let mut set_depth = false;
+        let abort = Arc::new(AtomicBool::new(false));
+        let mut board = board.clone();
+        let tt = Arc::clone(tt);
+        let search_abort = Arc::clone(&abort);
+
+        let handle = thread::spawn(move || {
+            let result = lazy_smp_with_abort(
+                &mut board,
+                mg,
+                &tt,
+                limits,
+                threads,
+                Some(&search_abort),
+                |result| UCI::info(result, &castle_settings, chess960),
+            );
+
+            while infinite && !search_abort.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
 
-        for part in parts {
-            if set_depth {
-                depth = part.parse().unwrap_or(6);
+            // Search stopped before the first iteration ended, any legal move is better than none
+            let best_move = result.best_move.or_else(|| {
+                let mut move_list = MoveList::new();
+                mg.generate_legal_moves(&board, &mut move_list);
+                (move_list.count > 0).then(|| move_list.moves[0])
+            });
+            match best_move {
+                Some(move_data) => {
+                    UCI::bestmove(&UCI::move_string(move_data, &castle_settings, chess960))
+                }
+                None => UCI::bestmove("0000"),
+            }
+            let _ = stdout().flush();
+        });
+
+        Some(SearchThread { handle, abort })
+    }
+
+    // "go perft N" or "divide N"
+    pub fn parse_perft(
+        command: &str,
+        board: &Board,
+        mg: &MoveGenerator,
+        threads: usize,
+        table: &PerftTable,
+    ) {
+        let depth = command
+            .split_whitespace()
+            .last()
+            .and_then(|v| v.parse::<i8>().ok())
+            .unwrap_or(1)
+            .max(1);
+
+        perft_test(board, mg, depth, threads, table);
+    }
+
+    // "perftsuite [file.epd | chess960] [max depth]", runs built-in suite if no file is given
+    pub fn parse_perft_suite(command: &str, mg: &MoveGenerator) {
+        let mut cases = standard_perft_suite();
+        let mut max_depth = i8::MAX;
+
+        for arg in command.split_whitespace().skip(1) {
+            if let Ok(depth) = arg.parse::<i8>() {
+                max_depth = depth;
+                continue;
+            }
+
+            if arg == "chess960" {
+                cases = chess960_perft_suite();
+                continue;
             }
 
+            match std::fs::read_to_string(arg) {
+                Ok(text) => match parse_perft_suite(&text) {
+                    Ok(parsed) => cases = parsed,
+                    Err(e) => {
+                        println!("Invalid perft suite {arg}: {e}");
+                        return;
+                    }
+                },
+                Err(e) => {
+                    println!("Can not read perft suite {arg}: {e}");
+                    return;
+                }
+            }
+        }
+
+        run_perft_suite(&cases, mg, max_depth);
+    }
+
+    // "testsuite FILE [movetime MS] [depth N] [nodes N]", one second per position by default
+    pub fn parse_test_suite(
+        command: &str,
+        mg: &MoveGenerator,
+        tt: &TranspositionTable,
+        threads: usize,
+    ) {
+        let Some(path) = command.split_whitespace().nth(1) else {
+            println!("Usage: testsuite FILE [movetime MS] [depth N] [nodes N]");
+            return;
+        };
+
+        let suite = match std::fs::read_to_string(path) {
+            Ok(text) => parse_test_suite(&text, mg),
+            Err(e) => {
+                println!("Can not read test suite {path}: {e}");
+                return;
+            }
+        };
+        let suite = match suite {
+            Ok(suite) => suite,
+            Err(e) => {
+                println!("Invalid test suite {path}: {e}");
+                return;
+            }
+        };
+
+        // Limits are read only after the path, which may contain the same words
+        let limit_args: Vec<&str> = command.split_whitespace().skip(2).collect();
+        let mut limits = SearchLimits {
+            movetime: Some(DEFAULT_TEST_SUITE_MOVETIME),
+            ..Default::default()
+        };
+        if limit_args
+            .iter()
+            .any(|arg| ["movetime", "depth", "nodes"].contains(arg))
+        {
+            limits = UCI::parse_limits(&limit_args.join(" "), Color::White);
+        }
+
+        run_test_suite(&suite, mg, tt, limits, threads);
+    }
+
+    pub fn parse_limits(command: &str, active_color: Color) -> SearchLimits {
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        let mut limits = SearchLimits::default();
+        let mut has_limits = false;
+
+        let mut time_left: Option<u64> = None;
+        let mut increment = 0u64;
+        let mut moves_to_go = 30u64;
+
+        let value = |i: usize| -> Option<u64> { parts.get(i + 1).and_then(|v| v.parse().ok()) };
+
+        for (i, part) in parts.iter().enumerate() {
             match part.to_lowercase().as_str() {
-                "go" => (),
-                "depth" => set_depth = true,
+                "depth" => {
+                    limits.depth = value(i)
+                        .unwrap_or(DEFAULT_DEPTH as u64)
+                        .min(limits.depth as u64) as i8;
+                    has_limits = true;
+                }
+                "movetime" => {
+                    limits.movetime = value(i).map(Duration::from_millis);
+                    has_limits = true;
+                }
+                "nodes" => {
+                    limits.nodes = value(i);
+                    has_limits = true;
+                }
+                "wtime" if active_color == Color::White => time_left = value(i),
+                "btime" if active_color == Color::Black => time_left = value(i),
+                "winc" if active_color == Color::White => increment = value(i).unwrap_or(0),
+                "binc" if active_color == Color::Black => increment = value(i).unwrap_or(0),
+                "movestogo" => moves_to_go = value(i).unwrap_or(30).max(1),
+                "infinite" => has_limits = true,
                 _ => (),
             }
         }
 
-        let mut search = Search::new(board, mg);
-        search.alpha_beta(-32000, 32000, depth);
-        if let Some(move_data) = search.best_move {
-            UCI::info(move_data, depth);
-            UCI::bestmove(move_data);
+        // Spend equal share of the remaining time on every move, keep some reserve for lag
+        if let Some(time_left) = time_left {
+            let budget = time_left / moves_to_go + increment / 2;
+            let budget = budget.min(time_left.saturating_sub(50)).max(1);
+            limits.movetime = Some(Duration::from_millis(budget));
+            has_limits = true;
+        }
+
+        if !has_limits {
+            limits.depth = DEFAULT_DEPTH;
         }
+
+        limits
+    }
+
+    pub fn parse_setoption(
+        command: &str,
+        tt: &mut TranspositionTable,
+        threads: &mut usize,
+        book: &mut OpeningBook,
+        chess960: &mut bool,
+    ) {
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        let name_index = parts.iter().position(|p| *p == "name");
+        let value_index = parts.iter().position(|p| *p == "value");
+
+        let (Some(name_index), Some(value_index)) = (name_index, value_index) else {
+            return;
+        };
+        let name = parts[name_index + 1..value_index].join(" ").to_lowercase();
+        let value = parts[value_index + 1..].join(" ");
+
+        match name.as_str() {
+            "threads" => {
+                if let Ok(n) = value.parse::<usize>() {
+                    *threads = n.clamp(1, MAX_THREADS);
+                }
+            }
+            "hash" => {
+                if let Ok(size_mb) = value.parse::<usize>() {
+                    tt.resize(size_mb.clamp(1, MAX_HASH_SIZE_MB));
+                }
+            }
+            "ownbook" => book.enabled = value == "true",
+            "uci_chess960" => *chess960 = value == "true",
+            "bookfile" => {
+                let path = if value == "<empty>" { "" } else { &value };
+                if let Err(error) = book.set_file(path) {
+                    println!("info string Can't load book {path}: {error}");
+                }
+            }
+            "evalfile" => {
+                let params = match value.as_str() {
+                    "<empty>" => Ok(EVAL_PARAMS),
+                    path => EvalParams::load(path),
+                };
+                match params {
+                    Ok(params) => set_active_params(params),
+                    Err(error) => println!("info string Can't load evaluation parameters: {error}"),
+                }
+            }
+            "bookdepth" => {
+                if let Ok(depth) = value.parse::<u16>() {
+                    book.depth = depth;
+                }
+            }
+            "bookbestmove" => {
+                book.selection = match value == "true" {
+                    true => BookSelection::Best,
+                    false => BookSelection::Weighted,
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // Fixed depth search over bench positions: "bench [depth]"
+    pub fn parse_bench(command: &str, mg: &MoveGenerator) {
+        let depth = command
+            .split_whitespace()
+            .nth(1)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_BENCH_DEPTH);
+
+        bench(mg, depth);
+    }
+
+    // Fixed time benchmark showing how nps scales with number of threads: "scaling [movetime ms]"
+    pub fn parse_scaling(command: &str, mg: &MoveGenerator, threads: usize) {
+        let movetime = command
+            .split_whitespace()
+            .nth(1)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(5000);
+        let max_threads = if threads > 1 {
+            threads
+        } else {
+            thread::available_parallelism().map_or(1, |n| n.get())
+        };
+
+        scaling_benchmark(
+            mg,
+            &BENCH_POSITIONS,
+            Duration::from_millis(movetime),
+            max_threads,
+        );
     }
 }
 
@@ -173,6 +604,16 @@ impl UCI {
     pub fn id() {
         println!("id name ChessDiplomaEngine");
         println!("id author AkulaFish");
+        println!(
+            "option name Hash type spin default {DEFAULT_HASH_SIZE_MB} min 1 max {MAX_HASH_SIZE_MB}"
+        );
+        println!("option name Threads type spin default 1 min 1 max {MAX_THREADS}");
+        println!("option name OwnBook type check default false");
+        println!("option name BookFile type string default <empty>");
+        println!("option name BookDepth type spin default {DEFAULT_BOOK_DEPTH} min 1 max 255");
+        println!("option name BookBestMove type check default false");
+        println!("option name UCI_Chess960 type check default false");
+        println!("option name EvalFile type string default <empty>");
     }
 
     pub fn readyok() {
@@ -183,11 +624,89 @@ impl UCI {
         println!("uciok");
     }
 
-    pub fn bestmove(move_data: Move) {
-        println!("bestmove {}", move_data.to_uci_string())
+    pub fn bestmove(move_string: &str) {
+        println!("bestmove {move_string}")
+    }
+
+    pub fn info(result: &SearchResult, castle_settings: &CastleAvailability, chess960: bool) {
+        let Some(move_data) = result.best_move else {
+            return;
+        };
+
+        let score = if result.score.abs() > MATE_SCORE {
+            // Number of moves (not plies) until mate
+            let plies = MATE_VALUE - result.score.abs();
+            format!("mate {}", result.score.signum() * ((plies + 1) / 2))
+        } else {
+            format!("cp {}", result.score)
+        };
+
+        println!(
+            "info depth {} score {score} nodes {} nps {} time {} pv {}",
+            result.depth,
+            result.nodes,
+            result.nps(),
+            result.elapsed.as_millis(),
+            UCI::move_string(move_data, castle_settings, chess960)
+        );
+    }
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infinite_search_stops() {
+        let mg = MoveGenerator::shared();
+        let tt = Arc::new(TranspositionTable::new(1));
+        let board = Fen::to_board(_START_FEN);
+
+        let search = UCI::parse_go(
+            "go infinite",
+            &board,
+            mg,
+            &tt,
+            2,
+            &OpeningBook::default(),
+            false,
+        );
+        thread::sleep(Duration::from_millis(50));
+        search.unwrap().stop();
+
+        // Search thread is gone, the table is not shared anymore
+        assert_eq!(Arc::strong_count(&tt), 1);
     }
 
-    pub fn info(move_data: Move, depth: i8) {
-        println!("info depth {depth} pv {}", move_data.to_uci_string());
+    #[test]
+    fn test_chess960_castling_moves() {
+        let mg = MoveGenerator::shared();
+        let board = Fen::to_board("1r2k1r1/1p4p1/8/8/8/8/1P4P1/1R2K1R1 w GBgb - 0 1");
+        let castle_settings = board.castle_settings();
+
+        let king_side = UCI::parse_move("e1g1", &board, mg).unwrap();
+        let queen_side = UCI::parse_move("e1b1", &board, mg).unwrap();
+        assert!(king_side.castling() && queen_side.castling());
+        assert_eq!(UCI::move_string(queen_side, castle_settings, true), "e1b1");
+        assert_eq!(UCI::move_string(queen_side, castle_settings, false), "e1c1");
+        assert_eq!(UCI::parse_move("e1c1", &board, mg), Some(queen_side));
+
+        let mut board = board.clone();
+        board.make_move(queen_side, mg);
+        assert_eq!(
+            Fen::from_board(&board),
+            "1r2k1r1/1p4p1/8/8/8/8/1P4P1/2KR2R1 b kq - 1 1"
+        );
+
+        // King next to its target square: plain king move unless the rook is taken
+        let board = Fen::to_board("4k3/8/8/8/8/8/8/5K1R w K - 0 1");
+        let king_move = UCI::parse_move("f1g1", &board, mg).unwrap();
+        let castling = UCI::parse_move("f1h1", &board, mg).unwrap();
+        assert!(!king_move.castling());
+        assert!(castling.castling());
+        assert_eq!(castling.target_square(), Square::G1);
     }
 }
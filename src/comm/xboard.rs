@@ -0,0 +1,399 @@
+/*
+ * CECP (XBoard / WinBoard) protocol, version 2.
+ * https://www.gnu.org/software/xboard/engine-intf.html
+ *
+ * Unlike UCI, the engine keeps track of the game itself: GUI sends moves one by one
+ * and engine answers with its own move whenever it is engine's turn and it is not in force mode.
+ */
+
+use std::{
+    io::{stdin, stdout, Write},
+    time::Duration,
+};
+
+use crate::{
+    board_repr::{board::Board, fen::Fen, piece::Color},
+    move_generation::{
+        generator::MoveGenerator,
+        move_list::MoveList,
+        moves::{Move, MoveType},
+    },
+    search::{
+        smp::{lazy_smp, SearchLimits, SearchResult, MAX_THREADS},
+        transposition::{TranspositionTable, MAX_HASH_SIZE_MB},
+    },
+    _START_FEN,
+};
+
+use super::uci::UCI;
+
+// Used when GUI did not tell how many moves are left until the next time control
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+
+pub struct XBoard {
+    board: Board,
+    mg: &'static MoveGenerator,
+    tt: TranspositionTable,
+    threads: usize,
+
+    // In force mode engine only plays moves it receives and never thinks on its own
+    force_mode: bool,
+    engine_color: Color,
+    post: bool,
+    // 'variant fischerandom', castling is sent and received as O-O / O-O-O
+    chess960: bool,
+
+    // Time control
+    max_depth: Option<i8>,
+    time_per_move: Option<Duration>,
+    moves_per_session: u64,
+    increment: Duration,
+    time_left: Duration,
+}
+
+impl XBoard {
+    pub fn xboard_loop() {
+        let mut xboard = XBoard::new();
+
+        loop {
+            let _ = stdout().flush();
+            let mut command = String::new();
+            let read = stdin()
+                .read_line(&mut command)
+                .expect("Can't take user input");
+
+            // End of input, GUI is gone
+            if read == 0 {
+                break;
+            }
+
+            let command = command.trim();
+            if command == "quit" {
+                break;
+            }
+
+            xboard.handle_command(command);
+        }
+    }
+
+    fn new() -> Self {
+        Self {
+            board: Fen::to_board(_START_FEN),
+            mg: MoveGenerator::shared(),
+            tt: TranspositionTable::default(),
+            threads: 1,
+
+            force_mode: false,
+            engine_color: Color::Black,
+            post: false,
+            chess960: false,
+
+            max_depth: None,
+            time_per_move: None,
+            moves_per_session: 0,
+            increment: Duration::default(),
+            time_left: Duration::from_secs(60),
+        }
+    }
+
+    pub fn handle_command(&mut self, command: &str) {
+        let (name, args) = command.split_once(' ').unwrap_or((command, ""));
+        let args = args.trim();
+
+        match name {
+            "xboard" => println!(),
+            "protover" => XBoard::features(),
+            "new" => {
+                self.board.from_fen(_START_FEN);
+                self.tt.clear();
+                self.force_mode = false;
+                self.engine_color = Color::Black;
+                self.max_depth = None;
+                self.time_per_move = None;
+                self.chess960 = false;
+            }
+            "variant" => self.chess960 = args == "fischerandom",
+            "setboard" => match XBoard::parse_position(args, self.mg) {
+                Ok(board) => self.board = board,
+                Err(_) => println!("tellusererror Illegal position"),
+            },
+            "force" => self.force_mode = true,
+            "go" => {
+                self.force_mode = false;
+                self.engine_color = self.board.active_color();
+                self.think();
+            }
+            "usermove" => self.user_move(args),
+            "level" => self.parse_level(args),
+            "st" => {
+                self.time_per_move = args.parse().ok().map(Duration::from_secs);
+            }
+            "sd" => self.max_depth = args.parse().ok(),
+            // Clock values are given in centiseconds
+            "time" => {
+                if let Ok(centiseconds) = args.parse::<u64>() {
+                    self.time_left = Duration::from_millis(centiseconds * 10);
+                }
+            }
+            "otim" => (),
+            "undo" => self.take_back(1),
+            "remove" => self.take_back(2),
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "result" => self.force_mode = true,
+            "ping" => println!("pong {args}"),
+            "cores" => {
+                if let Ok(n) = args.parse::<usize>() {
+                    self.threads = n.clamp(1, MAX_THREADS);
+                }
+            }
+            "memory" => {
+                if let Ok(size_mb) = args.parse::<usize>() {
+                    self.tt.resize(size_mb.clamp(1, MAX_HASH_SIZE_MB));
+                }
+            }
+            _ => {
+                // Protocol v1 GUIs send moves without 'usermove' prefix
+                if self.parse_move(command).is_some() {
+                    self.user_move(command);
+                }
+            }
+        }
+    }
+
+    fn features() {
+        println!("feature done=0");
+        println!("feature myname=\"ChessDiplomaEngine\"");
+        println!("feature ping=1 setboard=1 usermove=1 time=1 colors=0 reuse=1");
+        println!("feature sigint=0 sigterm=0 draw=0 analyze=0 smp=1 memory=1");
+        println!("feature variants=\"normal,fischerandom\"");
+        println!("feature done=1");
+    }
+
+    // FEN from 'setboard', see 'Board::validate' for what makes a position illegal
+    fn parse_position(fen: &str, mg: &MoveGenerator) -> Result<Board, String> {
+        let board = Fen::try_to_board(fen)?;
+        board.validate(mg)?;
+
+        Ok(board)
+    }
+
+    // Coordinate move, or O-O / O-O-O castling as used by 'fischerandom'
+    fn parse_move(&self, move_str: &str) -> Option<Move> {
+        let king_side = match move_str {
+            "O-O" | "o-o" | "0-0" => true,
+            "O-O-O" | "o-o-o" | "0-0-0" => false,
+            _ => return UCI::parse_move(move_str, &self.board, self.mg),
+        };
+
+        let mut move_list = MoveList::new();
+        self.mg
+            .generate_moves(&self.board, &mut move_list, MoveType::All);
+        move_list
+            .into_iter()
+            .find(|m| m.castling() && (m.target_square().file() == 6) == king_side)
+    }
+
+    // Castling in Chess960 is written as O-O / O-O-O, king squares would be ambiguous otherwise
+    fn move_string(move_data: Move, chess960: bool) -> String {
+        if !chess960 || !move_data.castling() {
+            return move_data.to_uci_string();
+        }
+
+        match move_data.target_square().file() {
+            6 => String::from("O-O"),
+            _ => String::from("O-O-O"),
+        }
+    }
+
+    fn user_move(&mut self, move_str: &str) {
+        let move_data = self.parse_move(move_str);
+
+        let is_legal = match move_data {
+            Some(m) => self.board.make_move(m, self.mg),
+            None => false,
+        };
+        if !is_legal {
+            println!("Illegal move: {move_str}");
+            return;
+        }
+
+        if self.report_game_end() {
+            return;
+        }
+
+        if !self.force_mode && self.board.active_color() == self.engine_color {
+            self.think();
+        }
+    }
+
+    fn take_back(&mut self, moves: usize) {
+        for _ in 0..moves {
+            if self.board.history.is_empty() {
+                break;
+            }
+            self.board.unmake_move();
+        }
+    }
+
+    // level MPS BASE INC, where BASE is either minutes or minutes:seconds
+    fn parse_level(&mut self, args: &str) {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if parts.len() != 3 {
+            return;
+        }
+
+        self.moves_per_session = parts[0].parse().unwrap_or(0);
+        self.increment = parts[2]
+            .parse::<f64>()
+            .map(Duration::from_secs_f64)
+            .unwrap_or_default();
+
+        let base_seconds = match parts[1].split_once(':') {
+            Some((minutes, seconds)) => {
+                minutes.parse::<u64>().unwrap_or(0) * 60 + seconds.parse::<u64>().unwrap_or(0)
+            }
+            None => parts[1].parse::<u64>().unwrap_or(0) * 60,
+        };
+        self.time_left = Duration::from_secs(base_seconds);
+        self.time_per_move = None;
+    }
+
+    fn search_limits(&self) -> SearchLimits {
+        let mut limits = SearchLimits::default();
+
+        if let Some(depth) = self.max_depth {
+            limits.depth = depth.clamp(1, limits.depth);
+        }
+
+        let movetime = match self.time_per_move {
+            Some(time) => time,
+            None => {
+                let moves_to_go = if self.moves_per_session > 0 {
+                    let played = (self.board.game_state.fullmove_number as u64).saturating_sub(1);
+                    self.moves_per_session - played % self.moves_per_session
+                } else {
+                    DEFAULT_MOVES_TO_GO
+                };
+
+                // Keep some reserve for communication lag
+                let budget = self.time_left / moves_to_go as u32 + self.increment / 2;
+                budget.min(self.time_left.saturating_sub(Duration::from_millis(50)))
+            }
+        };
+        limits.movetime = Some(movetime.max(Duration::from_millis(1)));
+
+        limits
+    }
+
+    fn think(&mut self) {
+        let limits = self.search_limits();
+        let post = self.post;
+        let chess960 = self.chess960;
+
+        let result = lazy_smp(
+            &mut self.board,
+            self.mg,
+            &self.tt,
+            limits,
+            self.threads,
+            |result| {
+                if post {
+                    XBoard::thinking_output(result, chess960);
+                }
+            },
+        );
+
+        let Some(move_data) = result.best_move else {
+            return;
+        };
+
+        let move_string = XBoard::move_string(move_data, self.chess960);
+        self.board.make_move(move_data, self.mg);
+        println!("move {move_string}");
+
+        self.report_game_end();
+    }
+
+    // ply score time nodes pv, where time is in centiseconds
+    fn thinking_output(result: &SearchResult, chess960: bool) {
+        if let Some(move_data) = result.best_move {
+            println!(
+                "{} {} {} {} {}",
+                result.depth,
+                result.score,
+                result.elapsed.as_millis() / 10,
+                result.nodes,
+                XBoard::move_string(move_data, chess960)
+            );
+        }
+    }
+
+    // Prints result if the game is over by checkmate or stalemate
+    fn report_game_end(&self) -> bool {
+        let mut board = self.board.clone();
+        let mut move_list = MoveList::new();
+        self.mg
+            .generate_moves(&board, &mut move_list, MoveType::All);
+
+        for move_data in move_list {
+            if board.make_move(move_data, self.mg) {
+                return false;
+            }
+        }
+
+        if !board.is_king_in_check(self.mg) {
+            println!("1/2-1/2 {{Stalemate}}");
+        } else if board.active_color() == Color::White {
+            println!("0-1 {{Black mates}}");
+        } else {
+            println!("1-0 {{White mates}}");
+        }
+
+        true
+    }
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_setboard_rejects_illegal_positions() {
+        let mg = MoveGenerator::shared();
+
+        assert!(XBoard::parse_position(_START_FEN, mg).is_ok());
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1",
+            "rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1",
+            "4k3/8/8/8/8/8/8/4KK2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K2r b - - 0 1",
+            "4k3/4R3/8/8/8/8/8/4K3 w - - 0 1",
+        ] {
+            assert!(XBoard::parse_position(fen, mg).is_err(), "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_fischerandom_castling() {
+        let mut xboard = XBoard::new();
+        xboard.handle_command("variant fischerandom");
+        xboard.handle_command("setboard 1r2k1r1/1p4p1/8/8/8/8/1P4P1/1R2K1R1 w GBgb - 0 1");
+
+        let king_side = xboard.parse_move("O-O").unwrap();
+        let queen_side = xboard.parse_move("O-O-O").unwrap();
+        assert_eq!(XBoard::move_string(king_side, true), "O-O");
+        assert_eq!(XBoard::move_string(queen_side, true), "O-O-O");
+        assert_eq!(XBoard::move_string(king_side, false), "e1g1");
+
+        xboard.handle_command("force");
+        xboard.handle_command("usermove O-O");
+        assert_eq!(
+            Fen::from_board(&xboard.board),
+            "1r2k1r1/1p4p1/8/8/8/8/1P4P1/1R3RK1 b kq - 1 1"
+        );
+    }
+}
@@ -1,6 +1,14 @@
-use crate::board_repr::{board::Board, piece::Color, square::Square};
+use std::sync::RwLock;
 
-type Psqt = [i16; 64];
+use crate::board_repr::{board::Board, piece::Color};
+
+pub type Psqt = [i16; 64];
+
+// Piece kinds follow the order of 'Piece': pawn, queen, king, bishop, knight, rook
+pub const PIECE_KINDS: usize = 6;
+pub const KIND_NAMES: [&str; PIECE_KINDS] = ["PAWN", "QUEEN", "KING", "BISHOP", "KNIGHT", "ROOK"];
+
+const MATERIAL: [i16; PIECE_KINDS] = [100, 1000, 10000, 350, 300, 500];
 
 #[rustfmt::skip]
 const KING_SCORE: Psqt = [
@@ -50,6 +58,8 @@ const KNIGHT_SCORE: Psqt = [
     -5, -10,   0,   0,   0,   0, -10,  -5,
 ];
 
+const QUEEN_SCORE: Psqt = [0; 64];
+
 #[rustfmt::skip]
 const PAWN_SCORE: Psqt = [
     90,  90,  90,  90,  90,  90,  90,  90,
@@ -75,35 +85,143 @@ pub const FLIP: [usize; 64] = [
 ];
 
 /*
- * Evaluates the position on the board.
- * Negative value if black is winning, positive - if white is winning.
+ * Evaluation parameters: material value and piece-square table of every piece kind.
+ * Tables are written from white's side, black pieces look them up through 'FLIP'.
+ * Evaluation is linear in the parameters, which is what the tuner relies on.
  */
-pub fn evaluate(board: &Board) -> i16 {
-    let mut score = 0i16;
-
-    for (square_index, piece) in board.piece_by_square.iter().enumerate() {
-        let square = Square::get_by_index(square_index as u8);
-        score += piece.eval_value();
-        let psqt = match piece {
-            p if p.is_king() => KING_SCORE,
-            p if p.is_rook() => ROOK_SCORE,
-            p if p.is_bishop() => BISHOP_SCORE,
-            p if p.is_knight() => KNIGHT_SCORE,
-            p if p.is_pawn() => PAWN_SCORE,
-            _ => [0; 64],
-        };
-        let square_index = if piece.color() == Color::White {
-            square as usize
-        } else {
-            FLIP[square as usize]
-        };
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalParams {
+    pub material: [i16; PIECE_KINDS],
+    pub psqt: [Psqt; PIECE_KINDS],
+}
+
+pub const EVAL_PARAMS: EvalParams = EvalParams {
+    material: MATERIAL,
+    psqt: [
+        PAWN_SCORE,
+        QUEEN_SCORE,
+        KING_SCORE,
+        BISHOP_SCORE,
+        KNIGHT_SCORE,
+        ROOK_SCORE,
+    ],
+};
+
+// Parameters picked up by new searches, replaced by the 'EvalFile' option
+static ACTIVE_PARAMS: RwLock<EvalParams> = RwLock::new(EVAL_PARAMS);
+
+pub fn active_params() -> EvalParams {
+    ACTIVE_PARAMS.read().unwrap().clone()
+}
+
+pub fn set_active_params(params: EvalParams) {
+    *ACTIVE_PARAMS.write().unwrap() = params;
+}
+
+impl EvalParams {
+    pub const COUNT: usize = PIECE_KINDS + PIECE_KINDS * 64;
+
+    // Flat layout: material values, then piece-square tables one after another
+    pub fn to_vector(&self) -> Vec<i16> {
+        let mut params = self.material.to_vec();
+        for psqt in &self.psqt {
+            params.extend_from_slice(psqt);
+        }
+        params
+    }
+
+    pub fn from_vector(params: &[i16]) -> Result<Self, String> {
+        if params.len() != Self::COUNT {
+            return Err(format!(
+                "Expected {} evaluation parameters, got {}",
+                Self::COUNT,
+                params.len()
+            ));
+        }
+
+        let mut result = EVAL_PARAMS;
+        result.material.copy_from_slice(&params[..PIECE_KINDS]);
+        for (kind, psqt) in result.psqt.iter_mut().enumerate() {
+            let start = PIECE_KINDS + kind * 64;
+            psqt.copy_from_slice(&params[start..start + 64]);
+        }
+        Ok(result)
+    }
 
-        score += psqt[square_index];
+    // Parameters file: numbers in the order of 'to_vector' separated by whitespace
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let params = text
+            .split_whitespace()
+            .map(|v| {
+                v.parse()
+                    .map_err(|_| format!("Invalid parameter value: {v}"))
+            })
+            .collect::<Result<Vec<i16>, String>>()?;
+        Self::from_vector(&params)
     }
 
-    if board.active_color() == Color::White {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("Can not read {path}: {e}"))?;
+        Self::parse(&text)
+    }
+
+    // Score from white's point of view
+    pub fn evaluate_white(&self, board: &Board) -> i16 {
+        let mut score = 0i16;
+
+        for (square, piece) in board.piece_by_square.iter().enumerate() {
+            if piece.is_none() {
+                continue;
+            }
+
+            let kind = *piece as usize % PIECE_KINDS;
+            match piece.color() {
+                Color::White => score += self.material[kind] + self.psqt[kind][square],
+                _ => score -= self.material[kind] + self.psqt[kind][FLIP[square]],
+            }
+        }
+
         score
-    } else {
-        -score
     }
+
+    // Score from the side to move
+    pub fn evaluate(&self, board: &Board) -> i16 {
+        let score = self.evaluate_white(board);
+
+        if board.active_color() == Color::White {
+            score
+        } else {
+            -score
+        }
+    }
+}
+
+// Parameter indices of the position with their coefficients, evaluation is their dot product
+pub fn eval_features(board: &Board) -> Vec<(usize, i8)> {
+    let mut features = vec![];
+
+    for (square, piece) in board.piece_by_square.iter().enumerate() {
+        if piece.is_none() {
+            continue;
+        }
+
+        let kind = *piece as usize % PIECE_KINDS;
+        let (sign, square) = match piece.color() {
+            Color::White => (1, square),
+            _ => (-1, FLIP[square]),
+        };
+        features.push((kind, sign));
+        features.push((PIECE_KINDS + kind * 64 + square, sign));
+    }
+
+    features
+}
+
+/*
+ * Evaluates the position on the board.
+ * Positive value if the side to move is winning, negative - if the opponent is.
+ */
+pub fn evaluate(board: &Board) -> i16 {
+    EVAL_PARAMS.evaluate(board)
 }
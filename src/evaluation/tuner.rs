@@ -0,0 +1,601 @@
+/*
+ * Texel tuner for evaluation parameters.
+ * Positions labelled with the game result are resolved to quiet leaves with quiescence search,
+ * then the evaluation is fitted to the results through a sigmoid:
+ *
+ * E = 1/N * sum (R - 1 / (1 + 10^(-K * q / 400)))^2
+ *
+ * where R is the white score of the game and q is the white-relative evaluation of the leaf.
+ * K is fitted first with the starting parameters and stays fixed while they are tuned.
+ * Evaluation is linear in the parameters, so every position is kept as a short list of
+ * feature indices and the error gradient is exact.
+ *
+ * chess_engine_rust tune DATA [--params FILE] [--method adam|local] [--epochs N] [--rate R] [--out FILE]
+ *
+ * DATA is a datagen binary file ('.bin') or text lines "FEN | score | 1.0", "FEN [0.5]" or "FEN 1-0".
+ * Output ending with '.rs' is Rust source for 'eval.rs', anything else is a parameters file
+ * that can be passed back with '--params' or loaded by the engine with the 'EvalFile' option.
+ */
+
+use std::{fs, time::Instant};
+
+use crate::{
+    board_repr::{board::Board, fen::Fen, packed::PackedReader},
+    move_generation::{generator::MoveGenerator, move_list::MoveList, moves::MoveType},
+    search::{
+        negamax::{Search, INFINITY},
+        smp::{SearchLimits, SharedState},
+        transposition::TranspositionTable,
+    },
+};
+
+use super::eval::{eval_features, set_active_params, EvalParams, EVAL_PARAMS, KIND_NAMES};
+
+// Quiescence leaf is never further than this from the labelled position
+const MAX_RESOLVE_PLIES: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TuneMethod {
+    Adam,
+    LocalSearch,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TunerOptions {
+    pub method: TuneMethod,
+    // Adam epochs or local search passes over all parameters
+    pub epochs: usize,
+    // Adam step size in centipawns
+    pub learning_rate: f64,
+}
+
+impl Default for TunerOptions {
+    fn default() -> Self {
+        Self {
+            method: TuneMethod::Adam,
+            epochs: 500,
+            learning_rate: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TuningEntry {
+    // Parameter indices with their coefficients in the white-relative evaluation
+    pub features: Vec<(u16, i8)>,
+    pub result: f64,
+}
+
+impl TuningEntry {
+    pub fn evaluate(&self, params: &[f64]) -> f64 {
+        self.features
+            .iter()
+            .map(|(index, coefficient)| params[*index as usize] * *coefficient as f64)
+            .sum()
+    }
+}
+
+pub fn sigmoid(k: f64, score: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * score / 400.0))
+}
+
+// Game result from white's side: "1.0", "0.5", "0", "1-0", "1/2-1/2", "0-1"
+pub fn parse_result(text: &str) -> Option<f64> {
+    match text
+        .trim()
+        .trim_matches(|c| c == '[' || c == ']' || c == '"' || c == ';')
+    {
+        "1-0" => Some(1.0),
+        "1/2-1/2" | "1/2" => Some(0.5),
+        "0-1" => Some(0.0),
+        value => value.parse().ok().filter(|v| (0.0..=1.0).contains(v)),
+    }
+}
+
+// "FEN | score | result", "FEN [result]" or "FEN result"
+pub fn parse_labelled_position(line: &str) -> Result<(Board, f64), String> {
+    let (fen, result) = if let Some((fen, rest)) = line.split_once('|') {
+        (fen, rest.rsplit('|').next().unwrap_or(rest))
+    } else if let Some((fen, rest)) = line.split_once('[') {
+        (fen, rest)
+    } else {
+        line.trim()
+            .rsplit_once(char::is_whitespace)
+            .ok_or(format!("Missing game result: {line}"))?
+    };
+
+    let result = parse_result(result).ok_or(format!("Invalid game result: {line}"))?;
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    if fields.len() < 4 {
+        return Err(format!("Invalid FEN: {line}"));
+    }
+
+    let board = Fen::try_to_board(&fields.join(" "))?;
+    board.validate(MoveGenerator::shared())?;
+
+    Ok((board, result))
+}
+
+/*
+ * Plays the best capture sequence found by quiescence search until standing pat is best.
+ * Positions in check have no quiet leaf and are skipped.
+ */
+pub fn resolve_position(search: &mut Search) -> Option<Board> {
+    let mut plies = 0;
+
+    loop {
+        if !search.mg.checkers(search.board).empty() {
+            return None;
+        }
+        if plies == MAX_RESOLVE_PLIES {
+            break;
+        }
+
+        let mut move_list = MoveList::new();
+        search
+            .mg
+            .generate_moves(search.board, &mut move_list, MoveType::Capture);
+
+        let mut best = (search.eval_params.evaluate(search.board), None);
+        for index in 0..move_list.count as usize {
+            let move_data = move_list.moves[index];
+            if !search.board.make_move(move_data, search.mg) {
+                continue;
+            }
+            // captures only, quiet checks are not part of the leaf
+            let score = -search.quiescence(-INFINITY, INFINITY, -1);
+            search.board.unmake_move();
+
+            if score > best.0 {
+                best = (score, Some(move_data));
+            }
+        }
+
+        match best.1 {
+            Some(move_data) => search.board.make_legal_move(move_data),
+            None => break,
+        }
+        plies += 1;
+    }
+
+    Some(search.board.clone())
+}
+
+pub fn build_entries(positions: Vec<(Board, f64)>, mg: &MoveGenerator) -> Vec<TuningEntry> {
+    let tt = TranspositionTable::new(1);
+    let shared = SharedState::new(&tt, SearchLimits::default());
+
+    positions
+        .into_iter()
+        .filter_map(|(mut board, result)| {
+            let mut search = Search::new(&mut board, mg, &shared, 0);
+            let leaf = resolve_position(&mut search)?;
+            let features = eval_features(&leaf)
+                .into_iter()
+                .map(|(index, coefficient)| (index as u16, coefficient))
+                .collect();
+            Some(TuningEntry { features, result })
+        })
+        .collect()
+}
+
+pub fn load_positions(path: &str) -> Result<Vec<(Board, f64)>, String> {
+    if path.ends_with(".bin") {
+        let reader = PackedReader::open(path).map_err(|e| format!("Can not read {path}: {e}"))?;
+        let mut positions = vec![];
+        for record in reader {
+            let record = record.map_err(|e| format!("Can not read {path}: {e}"))?;
+            if let Some(result) = record.result {
+                let board = record.board.to_board()?;
+                board.validate(MoveGenerator::shared())?;
+                positions.push((board, result.white_score()));
+            }
+        }
+        return Ok(positions);
+    }
+
+    fs::read_to_string(path)
+        .map_err(|e| format!("Can not read {path}: {e}"))?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(parse_labelled_position)
+        .collect()
+}
+
+pub fn mean_squared_error(entries: &[TuningEntry], params: &[f64], k: f64) -> f64 {
+    let total: f64 = entries
+        .iter()
+        .map(|entry| (entry.result - sigmoid(k, entry.evaluate(params))).powi(2))
+        .sum();
+    total / entries.len().max(1) as f64
+}
+
+// Golden-section search of K in 0..10, the error is unimodal in K
+pub fn fit_k(entries: &[TuningEntry], params: &[f64]) -> f64 {
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut low, mut high) = (0.0, 10.0);
+
+    while high - low > 1e-4 {
+        let left = high - ratio * (high - low);
+        let right = low + ratio * (high - low);
+        if mean_squared_error(entries, params, left) < mean_squared_error(entries, params, right) {
+            high = right;
+        } else {
+            low = left;
+        }
+    }
+
+    (low + high) / 2.0
+}
+
+fn gradient(entries: &[TuningEntry], params: &[f64], k: f64) -> Vec<f64> {
+    let mut gradient = vec![0.0; params.len()];
+    let scale = 2.0 * k * 10f64.ln() / 400.0 / entries.len().max(1) as f64;
+
+    for entry in entries {
+        let s = sigmoid(k, entry.evaluate(params));
+        let step = (s - entry.result) * s * (1.0 - s) * scale;
+        for (index, coefficient) in &entry.features {
+            gradient[*index as usize] += step * *coefficient as f64;
+        }
+    }
+
+    gradient
+}
+
+/*
+ * Full batch Adam.
+ * Parameters stay fractional during the run and are rounded when written out.
+ */
+pub fn tune_adam(entries: &[TuningEntry], params: &mut [f64], k: f64, options: &TunerOptions) {
+    const BETA1: f64 = 0.9;
+    const BETA2: f64 = 0.999;
+    const EPSILON: f64 = 1e-8;
+
+    let mut momentum = vec![0.0; params.len()];
+    let mut velocity = vec![0.0; params.len()];
+
+    for epoch in 1..=options.epochs {
+        let gradient = gradient(entries, params, k);
+        let correction1 = 1.0 - BETA1.powi(epoch as i32);
+        let correction2 = 1.0 - BETA2.powi(epoch as i32);
+
+        for (i, g) in gradient.iter().enumerate() {
+            momentum[i] = BETA1 * momentum[i] + (1.0 - BETA1) * g;
+            velocity[i] = BETA2 * velocity[i] + (1.0 - BETA2) * g * g;
+            let m = momentum[i] / correction1;
+            let v = velocity[i] / correction2;
+            params[i] -= options.learning_rate * m / (v.sqrt() + EPSILON);
+        }
+
+        if epoch % 50 == 0 || epoch == options.epochs {
+            eprintln!(
+                "Epoch {epoch}: error {:.6}",
+                mean_squared_error(entries, params, k)
+            );
+        }
+    }
+}
+
+/*
+ * Classic Texel local search: every parameter is moved by one centipawn up or down
+ * while the error keeps going down. Only evaluations of positions having the parameter
+ * are updated, so a pass costs as much as a few error computations.
+ */
+pub fn tune_local_search(
+    entries: &[TuningEntry],
+    params: &mut [f64],
+    k: f64,
+    options: &TunerOptions,
+) {
+    let mut occurrences: Vec<Vec<(u32, i8)>> = vec![vec![]; params.len()];
+    for (i, entry) in entries.iter().enumerate() {
+        for (index, coefficient) in &entry.features {
+            occurrences[*index as usize].push((i as u32, *coefficient));
+        }
+    }
+    let mut evals: Vec<f64> = entries.iter().map(|e| e.evaluate(params)).collect();
+
+    // Error change over positions having the parameter when it is moved by 'delta'
+    let error_delta = |evals: &[f64], occurrences: &[(u32, i8)], delta: f64| -> f64 {
+        occurrences
+            .iter()
+            .map(|(i, coefficient)| {
+                let entry = &entries[*i as usize];
+                let eval = evals[*i as usize];
+                let new_eval = eval + delta * *coefficient as f64;
+                (entry.result - sigmoid(k, new_eval)).powi(2)
+                    - (entry.result - sigmoid(k, eval)).powi(2)
+            })
+            .sum()
+    };
+
+    for pass in 1..=options.epochs {
+        let mut improved = 0;
+
+        for (index, occurrences) in occurrences.iter().enumerate() {
+            if occurrences.is_empty() {
+                continue;
+            }
+            for delta in [1.0, -1.0] {
+                if error_delta(&evals, occurrences, delta) >= 0.0 {
+                    continue;
+                }
+                params[index] += delta;
+                for (i, coefficient) in occurrences {
+                    evals[*i as usize] += delta * *coefficient as f64;
+                }
+                improved += 1;
+                break;
+            }
+        }
+
+        eprintln!(
+            "Pass {pass}: {improved} parameters changed, error {:.6}",
+            mean_squared_error(entries, params, k)
+        );
+        if improved == 0 {
+            break;
+        }
+    }
+}
+
+pub fn round_params(params: &[f64]) -> Vec<i16> {
+    params
+        .iter()
+        .map(|p| p.round().clamp(i16::MIN as f64, i16::MAX as f64) as i16)
+        .collect()
+}
+
+// Tables in the layout of 'eval.rs', ready to replace the hand-written ones
+pub fn to_rust_source(params: &EvalParams) -> String {
+    let material: Vec<String> = params.material.iter().map(i16::to_string).collect();
+    let mut source = format!(
+        "const MATERIAL: [i16; PIECE_KINDS] = [{}];\n",
+        material.join(", ")
+    );
+
+    for (name, psqt) in KIND_NAMES.iter().zip(&params.psqt) {
+        source.push_str(&format!(
+            "\n#[rustfmt::skip]\nconst {name}_SCORE: Psqt = [\n"
+        ));
+        for rank in psqt.chunks(8) {
+            let values: Vec<String> = rank.iter().map(|v| format!("{v:>4}")).collect();
+            source.push_str(&format!("   {},\n", values.join(",")));
+        }
+        source.push_str("];\n");
+    }
+
+    source
+}
+
+pub fn to_params_file(params: &EvalParams) -> String {
+    let mut lines = vec![params
+        .material
+        .iter()
+        .map(i16::to_string)
+        .collect::<Vec<String>>()
+        .join(" ")];
+    for psqt in &params.psqt {
+        for rank in psqt.chunks(8) {
+            let values: Vec<String> = rank.iter().map(i16::to_string).collect();
+            lines.push(values.join(" "));
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+fn parse_options(
+    args: &[String],
+) -> Result<(String, Option<String>, TunerOptions, String), String> {
+    let mut options = TunerOptions::default();
+    let mut params = None;
+    let mut output = String::from("params.txt");
+    let mut args = args.iter();
+    let input = args.next().ok_or("Missing data file")?.clone();
+
+    while let Some(name) = args.next() {
+        let value = args.next().ok_or(format!("Missing value for {name}"))?;
+        let invalid = || format!("Invalid value for {name}: {value}");
+
+        match name.as_str() {
+            "--params" => params = Some(value.clone()),
+            "--method" => {
+                options.method = match value.as_str() {
+                    "adam" => TuneMethod::Adam,
+                    "local" => TuneMethod::LocalSearch,
+                    _ => return Err(invalid()),
+                }
+            }
+            "--epochs" => options.epochs = value.parse().map_err(|_| invalid())?,
+            "--rate" => options.learning_rate = value.parse().map_err(|_| invalid())?,
+            "--out" => output = value.clone(),
+            _ => return Err(format!("Unknown argument {name}")),
+        }
+    }
+
+    Ok((input, params, options, output))
+}
+
+// Command line entry: "chess_engine_rust tune DATA [--params FILE] [--method adam|local] [--epochs N] [--rate R] [--out FILE]"
+pub fn run_tuner(args: &[String]) {
+    let (input, params_file, options, output) = match parse_options(args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+
+    let start_params = match params_file.as_deref().map(EvalParams::load) {
+        None => EVAL_PARAMS,
+        Some(Ok(params)) => params,
+        Some(Err(e)) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+
+    let start = Instant::now();
+    let positions = match load_positions(&input) {
+        Ok(positions) => positions,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+    let loaded = positions.len();
+    // Leaves are resolved with the starting parameters
+    set_active_params(start_params.clone());
+    let entries = build_entries(positions, MoveGenerator::shared());
+    eprintln!(
+        "Positions: {loaded}, quiet leaves: {} ({:.1} s)",
+        entries.len(),
+        start.elapsed().as_secs_f64()
+    );
+    if entries.is_empty() {
+        return;
+    }
+
+    let mut params: Vec<f64> = start_params.to_vector().iter().map(|p| *p as f64).collect();
+    let k = fit_k(&entries, &params);
+    eprintln!(
+        "K = {k:.4}, starting error {:.6}",
+        mean_squared_error(&entries, &params, k)
+    );
+
+    match options.method {
+        TuneMethod::Adam => tune_adam(&entries, &mut params, k, &options),
+        TuneMethod::LocalSearch => tune_local_search(&entries, &mut params, k, &options),
+    }
+
+    let tuned = EvalParams::from_vector(&round_params(&params)).unwrap();
+    let text = match output.ends_with(".rs") {
+        true => to_rust_source(&tuned),
+        false => to_params_file(&tuned),
+    };
+    match fs::write(&output, text) {
+        Ok(()) => eprintln!("Written to {output}"),
+        Err(e) => eprintln!("Can not write {output}: {e}"),
+    }
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board_repr::piece::Piece, evaluation::eval::evaluate, _POSITION_5, _START_FEN,
+        _TRICKY_POSITION,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_params_vector_and_features() {
+        let vector = EVAL_PARAMS.to_vector();
+        assert_eq!(vector.len(), EvalParams::COUNT);
+        assert_eq!(EvalParams::from_vector(&vector).unwrap(), EVAL_PARAMS);
+        assert_eq!(
+            EvalParams::parse(&to_params_file(&EVAL_PARAMS)).unwrap(),
+            EVAL_PARAMS
+        );
+        assert!(EvalParams::from_vector(&vector[1..]).is_err());
+
+        // Move ordering takes material values from the same parameters
+        assert_eq!(Piece::WhitePawn.eval_value(), EVAL_PARAMS.material[0]);
+        assert_eq!(Piece::BlackKnight.eval_value(), -EVAL_PARAMS.material[4]);
+
+        let params: Vec<f64> = vector.iter().map(|p| *p as f64).collect();
+        for fen in [_START_FEN, _TRICKY_POSITION, _POSITION_5] {
+            let board = Fen::to_board(fen);
+            let entry = build_entries(vec![(board.clone(), 0.5)], MoveGenerator::shared());
+            let features: Vec<(u16, i8)> = eval_features(&board)
+                .into_iter()
+                .map(|(index, coefficient)| (index as u16, coefficient))
+                .collect();
+            let white_eval = TuningEntry {
+                features,
+                result: 0.5,
+            }
+            .evaluate(&params);
+
+            assert_eq!(white_eval, EVAL_PARAMS.evaluate_white(&board) as f64);
+            assert_eq!(entry.len(), 1);
+        }
+
+        // Mirrored position is worth the same for the other side
+        let board = Fen::to_board("4k3/8/8/3n4/8/8/PP6/4K3 w - - 0 1");
+        let mirrored = Fen::to_board("4k3/pp6/8/8/3N4/8/8/4K3 b - - 0 1");
+        assert_eq!(evaluate(&board), evaluate(&mirrored));
+    }
+
+    #[test]
+    fn test_parse_labelled_positions() {
+        let lines = [
+            ("4k3/8/8/8/8/8/8/4K2R w K - 0 1 | 480 | 1.0", 1.0),
+            ("4k3/8/8/8/8/8/8/4K2R w K - 0 1 [0.5]", 0.5),
+            ("4k3/8/8/8/8/8/8/4K2R w K - [1/2-1/2]", 0.5),
+            ("4k3/8/8/8/8/8/8/4K2R w K - 0 1 0-1", 0.0),
+        ];
+        for (line, result) in lines {
+            let (board, parsed) = parse_labelled_position(line).unwrap();
+            assert_eq!(parsed, result);
+            assert_eq!(Fen::from_board(&board), "4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+        }
+        assert!(parse_labelled_position("4k3/8/8/8/8/8/8/4K2R w K - 0 1 2.0").is_err());
+        assert!(parse_labelled_position("8/8/8/8/8/8/8/4K2R w K - 0 1 1.0").is_err());
+    }
+
+    #[test]
+    fn test_resolve_and_tune() {
+        let mg = MoveGenerator::shared();
+
+        // Hanging queen is taken before the position is evaluated, check can not be resolved
+        let positions = vec![
+            (Fen::to_board("4k3/8/8/3q4/4P3/8/8/4K3 w - - 0 1"), 1.0),
+            (Fen::to_board("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1"), 0.5),
+        ];
+        let entries = build_entries(positions, mg);
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].features.iter().all(|(index, _)| *index != 1));
+
+        // Extra pawn wins, the tuner has to raise its value from nothing
+        let mut positions = vec![];
+        for (fen, result) in [
+            ("4k3/8/8/8/8/8/3P4/4K3 w - - 0 1", 1.0),
+            ("4k3/3p4/8/8/8/8/8/4K3 w - - 0 1", 0.0),
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", 0.5),
+        ] {
+            positions.push((Fen::to_board(fen), result));
+        }
+        let entries = build_entries(positions, mg);
+        let mut params = vec![0.0; EvalParams::COUNT];
+        params[0] = 1.0;
+
+        let k = fit_k(
+            &entries,
+            &EVAL_PARAMS
+                .to_vector()
+                .iter()
+                .map(|p| *p as f64)
+                .collect::<Vec<_>>(),
+        );
+        assert!(k > 0.0);
+        let before = mean_squared_error(&entries, &params, k);
+
+        let options = TunerOptions {
+            epochs: 20,
+            ..Default::default()
+        };
+        let mut adam = params.clone();
+        tune_adam(&entries, &mut adam, k, &options);
+        tune_local_search(&entries, &mut params, k, &options);
+
+        assert!(mean_squared_error(&entries, &adam, k) < before);
+        assert!(mean_squared_error(&entries, &params, k) < before);
+        assert!(params[0] > 1.0);
+    }
+}
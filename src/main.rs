@@ -1,6 +1,17 @@
-use comm::uci::UCI;
+use std::{env, io::stdin};
+
+use book::builder::run_book_builder;
+use comm::{uci::UCI, xboard::XBoard};
+use evaluation::tuner::run_tuner;
+use move_generation::generator::MoveGenerator;
+use utils::{
+    bench::{bench, DEFAULT_BENCH_DEPTH},
+    datagen::run_datagen,
+    magic_finder::run_magic_finder,
+};
 
 pub mod board_repr;
+pub mod book;
 pub mod comm;
 pub mod evaluation;
 pub mod move_generation;
@@ -11,6 +22,8 @@ const _START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0
 const _POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
 const _POSITION_4: &str = "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
 const _POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/2N5/PPP2nPP/RNBQK2R b KQ - 1 8";
+// Position 5 of https://www.chessprogramming.org/Perft_Results
+const _PERFT_POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
 const _POSITION_6: &str =
     "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
 const _TRICKY_POSITION: &str =
@@ -21,6 +34,50 @@ const _PROMOTION: &str = "8/P7/8/8/8/8/p7/8 b - - 0 1";
 const _CASTLING: &str = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1";
 
 fn main() {
-    // Run UCI communication interface
-    UCI::uci_loop();
+    // Command line mode: "chess_engine_rust bench [depth]"
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("bench") {
+        let depth = args
+            .get(2)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_BENCH_DEPTH);
+        bench(MoveGenerator::shared(), depth);
+        return;
+    }
+
+    // Command line mode: "chess_engine_rust magics [--seed N] [--fixed-shift] [--attempts N] [--out FILE]"
+    if args.get(1).map(String::as_str) == Some("magics") {
+        run_magic_finder(&args[2..]);
+        return;
+    }
+
+    // Command line mode: "chess_engine_rust makebook GAMES.pgn [--out FILE] [--max-ply N] [--min-games N] [--min-score PERCENT]"
+    if args.get(1).map(String::as_str) == Some("makebook") {
+        run_book_builder(&args[2..]);
+        return;
+    }
+
+    // Command line mode: "chess_engine_rust datagen [--games N] [--nodes N] [--threads N] [--random-plies N] [--book FILE] [--seed N] [--out FILE] [--text FILE]"
+    if args.get(1).map(String::as_str) == Some("datagen") {
+        run_datagen(&args[2..]);
+        return;
+    }
+
+    // Command line mode: "chess_engine_rust tune DATA [--params FILE] [--method adam|local] [--epochs N] [--rate R] [--out FILE]"
+    if args.get(1).map(String::as_str) == Some("tune") {
+        run_tuner(&args[2..]);
+        return;
+    }
+
+    // Protocol is picked by the first command GUI sends
+    let mut first_command = String::new();
+    stdin()
+        .read_line(&mut first_command)
+        .expect("Can't take user input");
+
+    if first_command.trim() == "xboard" {
+        XBoard::xboard_loop();
+    } else {
+        UCI::uci_loop(Some(first_command));
+    }
 }
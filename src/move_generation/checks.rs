@@ -0,0 +1,320 @@
+use crate::board_repr::{
+    bit_board::BitBoard,
+    board::Board,
+    piece::{Color, Piece},
+};
+
+use super::{generator::MoveGenerator, move_list::MoveList};
+
+// Rank a pawn of the given color promotes on (ranks are counted from the 8th rank)
+const PROMOTION_RANKS: [u64; 2] = [0xff, 0xff << 56];
+
+/*
+ * Check related move generation.
+ * Evasions are generated from the checker bitboard and between rays, so they are strictly legal.
+ * Quiet checks are pseudo legal, like the other move types, and have to be verified by 'make_move'.
+ */
+impl MoveGenerator {
+    /*
+     * King moves, captures of the checker and interpositions on the line between king and checker.
+     * Only meaningful when the side to move is in check, otherwise nothing is generated.
+     */
+    pub fn generate_evasions(&self, board: &Board, move_list: &mut MoveList) {
+        let checkers = self.checkers(board);
+        if checkers.empty() {
+            return;
+        }
+
+        let color = board.active_color();
+        let friendly_occupancy = board.get_occupancies(color);
+        let opponent_occupancy = board.get_occupancies(color.opposite());
+        let occupancy = board.get_occupancies(Color::Both);
+        let king_square = self.king_square(board, color);
+
+        // King is taken off the board, so it can not step back along the line of a checking slider
+        let without_king = occupancy & !king_square.get_bitboard();
+        let mut king_targets = self.get_king_attack(king_square) & !friendly_occupancy;
+        while !king_targets.empty() {
+            let target_square = king_targets.lsb_bit_square();
+            king_targets.pop_bit_value(target_square);
+
+            if (self.attackers_to(board, target_square, without_king) & opponent_occupancy).empty()
+            {
+                self.add_move(
+                    board,
+                    Piece::WhiteKing.to_color(color),
+                    king_square,
+                    target_square.get_bitboard(),
+                    move_list,
+                );
+            }
+        }
+
+        // Only king can escape double check
+        if checkers.count_ones() > 1 {
+            return;
+        }
+
+        // Capture the checker or block the line between it and the king
+        let checker_square = checkers.lsb_bit_square();
+        let target_mask = checkers | self.between[king_square as usize][checker_square as usize];
+        let pinned = self.pinned(board, color);
+
+        // Pinned piece can never answer a check, it would have to leave its pin ray
+        let pawn = Piece::WhitePawn.to_color(color);
+        let mut pawn_board = board.bitboards[pawn as usize] & !pinned;
+        while !pawn_board.empty() {
+            let source_square = pawn_board.lsb_bit_square();
+            pawn_board.pop_bit_value(source_square);
+
+            let attacks = self.get_pawn_attack(source_square, color);
+            let mut moves =
+                (self.get_pawn_pushes(board, source_square) & target_mask) | (attacks & checkers);
+
+            // En-passant removes the checking pawn or blocks the line, position after it is checked directly
+            if let Some(en_passant_square) = board.game_state.en_passant_target {
+                if attacks.get_bit_value(en_passant_square)
+                    && self.is_legal_en_passant(
+                        board,
+                        king_square,
+                        source_square,
+                        en_passant_square,
+                    )
+                {
+                    moves.set_bit_value(en_passant_square);
+                }
+            }
+
+            self.add_move(board, pawn, source_square, moves, move_list);
+        }
+
+        for piece in [
+            Piece::WhiteQueen,
+            Piece::WhiteRook,
+            Piece::WhiteBishop,
+            Piece::WhiteKnight,
+        ] {
+            let piece = piece.to_color(color);
+            let mut piece_bitboard = board.bitboards[piece as usize] & !pinned;
+
+            while !piece_bitboard.empty() {
+                let source_square = piece_bitboard.lsb_bit_square();
+                piece_bitboard.pop_bit_value(source_square);
+
+                let attack = match piece {
+                    Piece::WhiteQueen | Piece::BlackQueen => {
+                        self.get_queen_attack(source_square, occupancy)
+                    }
+                    Piece::WhiteRook | Piece::BlackRook => {
+                        self.get_rook_attack(source_square, occupancy)
+                    }
+                    Piece::WhiteBishop | Piece::BlackBishop => {
+                        self.get_bishop_attack(source_square, occupancy)
+                    }
+                    _ => self.get_knight_attack(source_square),
+                };
+
+                self.add_move(board, piece, source_square, attack & target_mask, move_list);
+            }
+        }
+    }
+
+    /*
+     * Non-capturing moves that give direct or discovered check.
+     * Promotions and castling are not included.
+     */
+    pub fn generate_quiet_checks(&self, board: &Board, move_list: &mut MoveList) {
+        let color = board.active_color();
+        let occupancy = board.get_occupancies(Color::Both);
+        let empty_squares = !occupancy;
+        let king_square = self.king_square(board, color.opposite());
+
+        // Our pieces that uncover an attack of our slider on the opponent king when they move off the line
+        let discovered =
+            self.king_blockers(board, color.opposite(), color) & board.get_occupancies(color);
+
+        // Pawns check from the squares an opponent pawn on king square would attack
+        let pawn = Piece::WhitePawn.to_color(color);
+        let pawn_checks = self.get_pawn_attack(king_square, color.opposite());
+        let mut pawn_board = board.bitboards[pawn as usize];
+
+        while !pawn_board.empty() {
+            let source_square = pawn_board.lsb_bit_square();
+            pawn_board.pop_bit_value(source_square);
+
+            let pushes = self.get_pawn_pushes(board, source_square)
+                & !BitBoard::from(PROMOTION_RANKS[color as usize]);
+            let mut moves = pushes & pawn_checks;
+            if discovered.get_bit_value(source_square) {
+                moves |= pushes & !self.line[king_square as usize][source_square as usize];
+            }
+
+            self.add_move(board, pawn, source_square, moves, move_list);
+        }
+
+        for piece in [
+            Piece::WhiteQueen,
+            Piece::WhiteRook,
+            Piece::WhiteBishop,
+            Piece::WhiteKnight,
+            Piece::WhiteKing,
+        ] {
+            // Squares from which the piece attacks the opponent king
+            let direct_checks = match piece {
+                Piece::WhiteQueen => self.get_queen_attack(king_square, occupancy),
+                Piece::WhiteRook => self.get_rook_attack(king_square, occupancy),
+                Piece::WhiteBishop => self.get_bishop_attack(king_square, occupancy),
+                Piece::WhiteKnight => self.get_knight_attack(king_square),
+                _ => BitBoard::default(),
+            };
+
+            let piece = piece.to_color(color);
+            let mut piece_bitboard = board.bitboards[piece as usize];
+
+            while !piece_bitboard.empty() {
+                let source_square = piece_bitboard.lsb_bit_square();
+                piece_bitboard.pop_bit_value(source_square);
+
+                let targets = match piece {
+                    Piece::WhiteQueen | Piece::BlackQueen => {
+                        self.get_queen_attack(source_square, occupancy)
+                    }
+                    Piece::WhiteRook | Piece::BlackRook => {
+                        self.get_rook_attack(source_square, occupancy)
+                    }
+                    Piece::WhiteBishop | Piece::BlackBishop => {
+                        self.get_bishop_attack(source_square, occupancy)
+                    }
+                    Piece::WhiteKnight | Piece::BlackKnight => {
+                        self.get_knight_attack(source_square)
+                    }
+                    _ => self.get_king_attack(source_square),
+                } & empty_squares;
+
+                let mut moves = targets & direct_checks;
+                if discovered.get_bit_value(source_square) {
+                    moves |= targets & !self.line[king_square as usize][source_square as usize];
+                }
+
+                self.add_move(board, piece, source_square, moves, move_list);
+            }
+        }
+    }
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board_repr::fen::Fen, move_generation::moves::MoveType, _EN_PASSANT, _POSITION_3,
+        _POSITION_4, _POSITION_5, _POSITION_6, _START_FEN, _TRICKY_POSITION,
+    };
+
+    use super::*;
+
+    fn sorted(move_list: &MoveList) -> Vec<u32> {
+        let mut moves: Vec<u32> = move_list.moves[..move_list.count as usize]
+            .iter()
+            .map(|m| m.data())
+            .collect();
+        moves.sort();
+        moves
+    }
+
+    // Compares both generators with filtering all legal moves, for every position of the tree
+    fn assert_check_moves(board: &mut Board, mg: &MoveGenerator, depth: i8) {
+        let mut legal = MoveList::new();
+        mg.generate_legal_moves(board, &mut legal);
+        let in_check = !mg.checkers(board).empty();
+
+        let mut evasions = MoveList::new();
+        mg.generate_moves(board, &mut evasions, MoveType::Evasions);
+        if in_check {
+            assert_eq!(sorted(&evasions), sorted(&legal));
+        } else {
+            assert_eq!(evasions.count, 0);
+        }
+
+        let mut expected = vec![];
+        for move_data in &legal.moves[..legal.count as usize] {
+            let quiet = move_data.captured_piece().is_none()
+                && !move_data.en_passant()
+                && !move_data.castling()
+                && move_data.promoted_piece().is_none();
+            board.make_legal_move(*move_data);
+            if quiet && !mg.checkers(board).empty() {
+                expected.push(move_data.data());
+            }
+            board.unmake_move();
+        }
+
+        let mut quiet_checks = MoveList::new();
+        mg.generate_moves(board, &mut quiet_checks, MoveType::QuietChecks);
+        // Quiet checks are pseudo legal, so pinned pieces have to be filtered out
+        let mut actual = vec![];
+        for move_data in quiet_checks {
+            if board.make_move(move_data, mg) {
+                board.unmake_move();
+                actual.push(move_data.data());
+            }
+        }
+        expected.sort();
+        actual.sort();
+        assert_eq!(actual, expected);
+
+        if depth > 1 {
+            for move_data in &legal.moves[..legal.count as usize] {
+                board.make_legal_move(*move_data);
+                assert_check_moves(board, mg, depth - 1);
+                board.unmake_move();
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_moves_match_legal_moves_filtering() {
+        let mg = MoveGenerator::shared();
+
+        for fen in [
+            _START_FEN,
+            _TRICKY_POSITION,
+            _POSITION_3,
+            _POSITION_4,
+            _POSITION_5,
+            _POSITION_6,
+            _EN_PASSANT,
+        ] {
+            assert_check_moves(&mut Fen::to_board(fen), mg, 3);
+        }
+    }
+
+    #[test]
+    fn test_evasions() {
+        let mg = MoveGenerator::shared();
+        let evasions = |fen: &str| {
+            let mut move_list = MoveList::new();
+            mg.generate_moves(&Fen::to_board(fen), &mut move_list, MoveType::Evasions);
+            let mut moves: Vec<String> = move_list.into_iter().map(|m| m.to_uci_string()).collect();
+            moves.sort();
+            moves
+        };
+
+        // Double check by knight and rook, only the king moves
+        assert_eq!(
+            evasions("4k3/8/8/8/8/5n2/8/R3K2r w - - 0 1"),
+            ["e1e2", "e1f2"]
+        );
+        // Checking pawn is captured en-passant
+        assert!(evasions("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1").contains(&String::from("e4d3")));
+
+        // Check related move types are not generated piece by piece
+        let board = Fen::to_board(_TRICKY_POSITION);
+        for move_type in [MoveType::Evasions, MoveType::QuietChecks] {
+            let mut move_list = MoveList::new();
+            mg.generate_piece_moves(Piece::WhiteQueen, &board, &mut move_list, move_type);
+            assert_eq!(move_list.count, 0);
+        }
+    }
+}
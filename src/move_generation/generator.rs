@@ -9,16 +9,26 @@ use super::{
     magics::{Magic, BISHOP_TABLE_SIZE, ROOK_TABLE_SIZE},
     move_list::MoveList,
     moves::{Move, MoveType},
+    pext::SliderBackend,
+    static_tables::{BISHOP_PEXT_TABLE_SIZE, ROOK_PEXT_TABLE_SIZE},
 };
 
 pub struct MoveGenerator {
-    pub king: [BitBoard; 64],
-    pub pawn: [[BitBoard; 64]; 2],
-    pub rook: [BitBoard; ROOK_TABLE_SIZE],
-    pub bishop: [BitBoard; BISHOP_TABLE_SIZE],
-    pub knight: [BitBoard; 64],
-    pub rook_magics: [Magic; 64],
-    pub bishop_magics: [Magic; 64],
+    pub king: &'static [BitBoard; 64],
+    pub pawn: &'static [[BitBoard; 64]; 2],
+    pub rook: &'static [BitBoard; ROOK_TABLE_SIZE],
+    pub bishop: &'static [BitBoard; BISHOP_TABLE_SIZE],
+    pub knight: &'static [BitBoard; 64],
+    pub rook_magics: &'static [Magic; 64],
+    pub bishop_magics: &'static [Magic; 64],
+    // PEXT backend is only set after BMI2 was detected, see 'with_backend'
+    pub(super) slider_backend: SliderBackend,
+    pub rook_pext: &'static [BitBoard; ROOK_PEXT_TABLE_SIZE],
+    pub bishop_pext: &'static [BitBoard; BISHOP_PEXT_TABLE_SIZE],
+    pub rook_pext_offsets: &'static [usize; 64],
+    pub bishop_pext_offsets: &'static [usize; 64],
+    pub between: &'static [[BitBoard; 64]; 64],
+    pub line: &'static [[BitBoard; 64]; 64],
 }
 
 /*
@@ -28,12 +38,20 @@ pub struct MoveGenerator {
  */
 impl MoveGenerator {
     pub fn get_bishop_attack(&self, square: Square, blocker: BitBoard) -> BitBoard {
+        if self.slider_backend == SliderBackend::Pext {
+            return self.pext_bishop_attack(square, blocker);
+        }
+
         let magic = self.bishop_magics[square as usize];
         let index = magic.index(blocker);
         self.bishop[index]
     }
 
     pub fn get_rook_attack(&self, square: Square, blocker: BitBoard) -> BitBoard {
+        if self.slider_backend == SliderBackend::Pext {
+            return self.pext_rook_attack(square, blocker);
+        }
+
         let magic = self.rook_magics[square as usize];
         let index = magic.index(blocker);
         self.rook[index]
@@ -68,11 +86,18 @@ impl MoveGenerator {
  */
 impl MoveGenerator {
     pub fn generate_moves(&self, board: &Board, move_list: &mut MoveList, move_type: MoveType) {
+        // Check related move types have their own generators
+        match move_type {
+            MoveType::Evasions => return self.generate_evasions(board, move_list),
+            MoveType::QuietChecks => return self.generate_quiet_checks(board, move_list),
+            _ => (),
+        }
+
         // Special case. Generate pawn moves
         self.generate_pawn_moves(board, move_list, move_type);
 
         // Special case. Generate castle moves
-        if move_type == MoveType::All || move_type == MoveType::Quite {
+        if move_type == MoveType::All || move_type == MoveType::Quiet {
             self.generate_castling_moves(board, move_list);
         }
 
@@ -116,6 +141,11 @@ impl MoveGenerator {
         move_list: &mut MoveList,
         move_type: MoveType,
     ) {
+        // Check related moves depend on the whole position, they come only from 'generate_moves'
+        if matches!(move_type, MoveType::Evasions | MoveType::QuietChecks) {
+            return;
+        }
+
         let occupancy = board.get_occupancies(Color::Both);
         let friendly_occupancy = board.get_occupancies(board.active_color());
         let opponent_occupancy = board.get_occupancies(board.opponent_color());
@@ -143,8 +173,9 @@ impl MoveGenerator {
 
             let moves = match move_type {
                 MoveType::All => attack & !friendly_occupancy,
-                MoveType::Quite => attack & occupancy,
+                MoveType::Quiet => attack & !occupancy,
                 MoveType::Capture => attack & opponent_occupancy,
+                MoveType::Evasions | MoveType::QuietChecks => BitBoard::default(),
             };
 
             self.add_move(board, piece, source_square, moves, move_list);
@@ -157,40 +188,18 @@ impl MoveGenerator {
         move_list: &mut MoveList,
         move_type: MoveType,
     ) {
-        // TODO: I don't like that ranks are reversed, fix this later.
-        let next_rank = match board.active_color() {
-            Color::White => -1,
-            Color::Black => 1,
-            Color::Both => panic!("Active color can not be BOTH."),
-        };
-        let pawns_rank = match board.active_color() {
-            Color::White => 6,
-            Color::Black => 1,
-            Color::Both => panic!("Active color can not be BOTH."),
-        };
-        let rotations_count = (64 + 8 * next_rank) as u32;
-
         let piece = Piece::WhitePawn.to_color(board.active_color());
         let mut piece_board = board.bitboards[piece as usize];
-        let occupancy = board.get_occupancies(Color::Both);
         let opponent_occupancy = board.get_occupancies(board.opponent_color());
-        let empty_squares = !occupancy;
 
         while !piece_board.empty() {
             let mut moves = BitBoard::default();
             let source_square = piece_board.lsb_bit_square();
             piece_board.pop_bit_value(source_square);
-            let next_square = source_square.add_rank(next_rank);
-
-            // Generate quite pawn moves
-            if move_type == MoveType::All || move_type == MoveType::Quite {
-                let single_push = next_square.get_bitboard() & empty_squares;
-                let double_push = if source_square.rank() == pawns_rank && !single_push.empty() {
-                    next_square.get_bitboard().rotate_left(rotations_count) & empty_squares
-                } else {
-                    BitBoard::default()
-                };
-                moves |= single_push | double_push;
+
+            // Generate quiet pawn moves
+            if move_type == MoveType::All || move_type == MoveType::Quiet {
+                moves |= self.get_pawn_pushes(board, source_square);
             }
 
             // Generate pawn captures
@@ -209,95 +218,102 @@ impl MoveGenerator {
         }
     }
 
+    // Single and double pushes of the pawn of the side to move
+    pub fn get_pawn_pushes(&self, board: &Board, source_square: Square) -> BitBoard {
+        // TODO: I don't like that ranks are reversed, fix this later.
+        let next_rank = match board.active_color() {
+            Color::White => -1,
+            Color::Black => 1,
+            Color::Both => panic!("Active color can not be BOTH."),
+        };
+        let pawns_rank = match board.active_color() {
+            Color::White => 6,
+            Color::Black => 1,
+            Color::Both => panic!("Active color can not be BOTH."),
+        };
+        let rotations_count = (64 + 8 * next_rank) as u32;
+        let empty_squares = !board.get_occupancies(Color::Both);
+        let next_square = source_square.add_rank(next_rank);
+
+        let single_push = next_square.get_bitboard() & empty_squares;
+        let double_push = if source_square.rank() == pawns_rank && !single_push.empty() {
+            next_square.get_bitboard().rotate_left(rotations_count) & empty_squares
+        } else {
+            BitBoard::default()
+        };
+
+        single_push | double_push
+    }
+
+    /*
+     * Castling for any king and rook placement (Chess960 included).
+     * King goes to the 'g' or 'c' file and rook next to it. Squares both of them pass have to be
+     * empty apart from the two castling pieces, and squares the king passes must not be attacked.
+     * Generated moves are legal.
+     */
     pub fn generate_castling_moves(&self, board: &Board, move_list: &mut MoveList) {
-        let source_square = board.bitboards
-            [Piece::WhiteKing.to_color(board.active_color()) as usize]
-            .lsb_bit_square();
+        let color = board.active_color();
+        let king = Piece::WhiteKing.to_color(color);
+        let rook = Piece::WhiteRook.to_color(color);
+        let source_square = board.bitboards[king as usize].lsb_bit_square();
 
         // Can not castle under check
         if self.is_square_attacked(source_square, board.opponent_color(), board) {
             return;
         }
 
+        let castle_settings = board.castle_settings();
+        let (king_side, queen_side) = match color {
+            Color::White => (
+                castle_settings.can_white_castle_king,
+                castle_settings.can_white_castle_queen,
+            ),
+            _ => (
+                castle_settings.can_black_castle_king,
+                castle_settings.can_black_castle_queen,
+            ),
+        };
         let occupancy = board.get_occupancies(Color::Both);
-        let white_kingside_blockers = Square::F1.get_bitboard() | Square::G1.get_bitboard();
-        let white_queenside_blockers =
-            Square::B1.get_bitboard() | Square::C1.get_bitboard() | Square::D1.get_bitboard();
-
-        let black_kingside_blockers = Square::F8.get_bitboard() | Square::G8.get_bitboard();
-        let black_queenside_blockers =
-            Square::B8.get_bitboard() | Square::C8.get_bitboard() | Square::D8.get_bitboard();
-
-        //White
-        if board.active_color() == Color::White {
-            // Kingside
-            if board.castle_settings().can_white_castle_king {
-                let is_kingside_blocked = !(white_kingside_blockers & occupancy).empty();
-
-                if !is_kingside_blocked
-                    && !self.is_square_attacked(Square::F1, Color::Black, board)
-                    && !self.is_square_attacked(Square::G1, Color::Black, board)
-                {
-                    self.add_move(
-                        board,
-                        Piece::WhiteKing,
-                        source_square,
-                        Square::G1.get_bitboard(),
-                        move_list,
-                    );
-                }
+        let opponent_occupancy = board.get_occupancies(board.opponent_color());
+
+        for (available, target_file) in [(king_side, 6), (queen_side, 2)] {
+            let target_square = Square::from_file_and_rank(target_file, source_square.rank());
+            let rook_square = castle_settings.rook_square(target_square);
+            let rook_target = castle_settings.rook_target(target_square);
+            if !available || board.piece_by_square[rook_square as usize] != rook {
+                continue;
             }
-            // Queenside
-            if board.castle_settings().can_white_castle_queen {
-                let is_queenside_blocked = !(white_queenside_blockers & occupancy).empty();
-                if !is_queenside_blocked
-                    && !self.is_square_attacked(Square::C1, Color::Black, board)
-                    && !self.is_square_attacked(Square::D1, Color::Black, board)
-                {
-                    self.add_move(
-                        board,
-                        Piece::WhiteKing,
-                        source_square,
-                        Square::C1.get_bitboard(),
-                        move_list,
-                    );
-                }
+
+            let king_path = self.between[source_square as usize][target_square as usize]
+                | target_square.get_bitboard();
+            let rook_path = self.between[rook_square as usize][rook_target as usize]
+                | rook_target.get_bitboard();
+            let others = occupancy & !source_square.get_bitboard() & !rook_square.get_bitboard();
+            if !((king_path | rook_path) & others).empty() {
+                continue;
             }
-        }
 
-        // Black
-        if board.active_color() == Color::Black {
-            // Kingside
-            if board.castle_settings().can_black_castle_king {
-                let is_kingside_blocked = !(black_kingside_blockers & occupancy).empty();
-                if !is_kingside_blocked
-                    && !self.is_square_attacked(Square::F8, Color::White, board)
-                    && !self.is_square_attacked(Square::G8, Color::White, board)
-                {
-                    self.add_move(
-                        board,
-                        Piece::BlackKing,
-                        source_square,
-                        Square::G8.get_bitboard(),
-                        move_list,
-                    );
-                }
+            // Castling rook may hide a slider that attacks the target square along the rank
+            let mut path = king_path;
+            let mut is_path_attacked = false;
+            while !path.empty() && !is_path_attacked {
+                let square = path.lsb_bit_square();
+                path.pop_bit_value(square);
+                is_path_attacked =
+                    !(self.attackers_to(board, square, others) & opponent_occupancy).empty();
             }
-            // Queenside
-            if board.castle_settings().can_black_castle_queen {
-                let is_queenside_blocked = !(black_queenside_blockers & occupancy).empty();
-                if !is_queenside_blocked
-                    && !self.is_square_attacked(Square::C8, Color::White, board)
-                    && !self.is_square_attacked(Square::D8, Color::White, board)
-                {
-                    self.add_move(
-                        board,
-                        Piece::BlackKing,
-                        source_square,
-                        Square::C8.get_bitboard(),
-                        move_list,
-                    );
-                }
+
+            if !is_path_attacked {
+                move_list.add_move(Move::encode_move(
+                    source_square,
+                    target_square,
+                    king,
+                    Piece::None,
+                    Piece::None,
+                    false,
+                    true,
+                    false,
+                ));
             }
         }
     }
@@ -322,7 +338,7 @@ impl MoveGenerator {
             let to_square = to_bb.lsb_bit_square();
             to_bb.pop_bit_value(to_square);
             let is_en_passant = match board.game_state.en_passant_target {
-                Some(en_passant_square) => en_passant_square == to_square,
+                Some(en_passant_square) => piece.is_pawn() && en_passant_square == to_square,
                 None => false,
             };
             let castling = piece.is_king() && ((to_square as i8 - source_square as i8).abs() == 2);
@@ -423,3 +439,65 @@ impl MoveGenerator {
         false
     }
 }
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board_repr::fen::Fen, _EN_PASSANT, _POSITION_3, _POSITION_4, _POSITION_5, _POSITION_6,
+        _START_FEN, _TRICKY_POSITION,
+    };
+
+    use super::*;
+
+    fn generate(board: &Board, mg: &MoveGenerator, move_type: MoveType) -> Vec<u32> {
+        let mut move_list = MoveList::new();
+        mg.generate_moves(board, &mut move_list, move_type);
+        move_list.into_iter().map(|m| m.data()).collect()
+    }
+
+    // Quiet and capture sets are disjoint and together give every pseudo legal move
+    fn assert_move_types_split_all(board: &mut Board, mg: &MoveGenerator, depth: i8) {
+        let occupancy = board.get_occupancies(Color::Both);
+        let quiet = generate(board, mg, MoveType::Quiet);
+        let captures = generate(board, mg, MoveType::Capture);
+        let mut all = generate(board, mg, MoveType::All);
+
+        for move_data in quiet.iter().map(|m| Move::from_data(*m)) {
+            assert!(!occupancy.get_bit_value(move_data.target_square()));
+        }
+
+        let mut combined: Vec<u32> = quiet.into_iter().chain(captures).collect();
+        combined.sort();
+        all.sort();
+        assert_eq!(combined, all);
+
+        if depth > 1 {
+            for move_data in all.into_iter().map(Move::from_data) {
+                if board.make_move(move_data, mg) {
+                    assert_move_types_split_all(board, mg, depth - 1);
+                    board.unmake_move();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_quiet_and_capture_moves_make_up_all_moves() {
+        let mg = MoveGenerator::shared();
+
+        for fen in [
+            _START_FEN,
+            _TRICKY_POSITION,
+            _POSITION_3,
+            _POSITION_4,
+            _POSITION_5,
+            _POSITION_6,
+            _EN_PASSANT,
+        ] {
+            assert_move_types_split_all(&mut Fen::to_board(fen), mg, 3);
+        }
+    }
+}
@@ -1,11 +1,18 @@
 use super::{
     generator::MoveGenerator,
-    tables::{
-        generate_bishop_attack_masks, generate_king_attack_masks, generate_knight_attack_masks,
-        generate_pawn_attack_masks, generate_rook_attack_masks,
+    pext::SliderBackend,
+    static_tables::{
+        BETWEEN, BISHOP_ATTACKS, BISHOP_MAGIC_TABLE, BISHOP_PEXT_ATTACKS, BISHOP_PEXT_OFFSETS,
+        KING_ATTACKS, KNIGHT_ATTACKS, LINE, PAWN_ATTACKS, ROOK_ATTACKS, ROOK_MAGIC_TABLE,
+        ROOK_PEXT_ATTACKS, ROOK_PEXT_OFFSETS,
     },
 };
 
+// Attack tables never change, so a single instance backs every search, thread and tool
+// PEXT generator is only handed out by 'shared()' after BMI2 was detected
+static SHARED_MAGIC_MOVE_GENERATOR: MoveGenerator = MoveGenerator::build(SliderBackend::Magic);
+static SHARED_PEXT_MOVE_GENERATOR: MoveGenerator = MoveGenerator::build(SliderBackend::Pext);
+
 impl Default for MoveGenerator {
     fn default() -> Self {
         Self::new()
@@ -13,21 +20,48 @@ impl Default for MoveGenerator {
 }
 
 impl MoveGenerator {
-    pub fn new() -> Self {
-        let king = generate_king_attack_masks();
-        let pawn = generate_pawn_attack_masks();
-        let knight = generate_knight_attack_masks();
-        let (bishop, bishop_magics) = generate_bishop_attack_masks();
-        let (rook, rook_magics) = generate_rook_attack_masks();
+    // Tables are computed at compile time, so creating a generator costs nothing.
+    // Magic backend works everywhere, use 'shared()' to get the fastest one for this CPU.
+    pub const fn new() -> Self {
+        Self::build(SliderBackend::Magic)
+    }
+
+    // SliderBackend::Pext falls back to magics if the CPU has no BMI2
+    pub fn with_backend(slider_backend: SliderBackend) -> Self {
+        match slider_backend {
+            SliderBackend::Pext if SliderBackend::pext_supported() => Self::build(slider_backend),
+            _ => Self::build(SliderBackend::Magic),
+        }
+    }
 
+    pub fn slider_backend(&self) -> SliderBackend {
+        self.slider_backend
+    }
+
+    // Callers make sure PEXT backend is only built for CPUs with BMI2
+    const fn build(slider_backend: SliderBackend) -> Self {
         Self {
-            king,
-            pawn,
-            knight,
-            bishop,
-            rook,
-            bishop_magics,
-            rook_magics,
+            king: &KING_ATTACKS,
+            pawn: &PAWN_ATTACKS,
+            knight: &KNIGHT_ATTACKS,
+            bishop: &BISHOP_ATTACKS,
+            rook: &ROOK_ATTACKS,
+            bishop_magics: &BISHOP_MAGIC_TABLE,
+            rook_magics: &ROOK_MAGIC_TABLE,
+            slider_backend,
+            rook_pext: &ROOK_PEXT_ATTACKS,
+            bishop_pext: &BISHOP_PEXT_ATTACKS,
+            rook_pext_offsets: &ROOK_PEXT_OFFSETS,
+            bishop_pext_offsets: &BISHOP_PEXT_OFFSETS,
+            between: &BETWEEN,
+            line: &LINE,
+        }
+    }
+
+    pub fn shared() -> &'static MoveGenerator {
+        match SliderBackend::detect() {
+            SliderBackend::Pext => &SHARED_PEXT_MOVE_GENERATOR,
+            SliderBackend::Magic => &SHARED_MAGIC_MOVE_GENERATOR,
         }
     }
 }
@@ -0,0 +1,478 @@
+use strum::IntoEnumIterator;
+
+use crate::board_repr::{
+    bit_board::BitBoard,
+    board::Board,
+    piece::{Color, Piece},
+    square::Square,
+};
+
+use super::{generator::MoveGenerator, move_list::MoveList};
+
+/*
+ * Attack queries used by legal move generation.
+ */
+impl MoveGenerator {
+    // Pieces of both colors attacking the square, sliders are blocked by the given occupancy
+    pub fn attackers_to(&self, board: &Board, square: Square, occupancy: BitBoard) -> BitBoard {
+        let pieces = |white: Piece, black: Piece| {
+            board.bitboards[white as usize] | board.bitboards[black as usize]
+        };
+        let queens = pieces(Piece::WhiteQueen, Piece::BlackQueen);
+
+        (self.get_pawn_attack(square, Color::Black) & board.bitboards[Piece::WhitePawn as usize])
+            | (self.get_pawn_attack(square, Color::White)
+                & board.bitboards[Piece::BlackPawn as usize])
+            | (self.get_knight_attack(square) & pieces(Piece::WhiteKnight, Piece::BlackKnight))
+            | (self.get_king_attack(square) & pieces(Piece::WhiteKing, Piece::BlackKing))
+            | (self.get_bishop_attack(square, occupancy)
+                & (pieces(Piece::WhiteBishop, Piece::BlackBishop) | queens))
+            | (self.get_rook_attack(square, occupancy)
+                & (pieces(Piece::WhiteRook, Piece::BlackRook) | queens))
+    }
+
+    // Every square attacked by pieces of the given color, occupied or not
+    pub fn attacks_by(&self, board: &Board, color: Color) -> BitBoard {
+        let occupancy = board.get_occupancies(Color::Both);
+        let mut attacks = BitBoard::default();
+
+        for piece in Piece::iter().filter(|p| !p.is_none() && p.color() == color) {
+            let mut piece_bitboard = board.bitboards[piece as usize];
+
+            while !piece_bitboard.empty() {
+                let square = piece_bitboard.lsb_bit_square();
+                piece_bitboard.pop_bit_value(square);
+
+                attacks |= match piece {
+                    Piece::WhitePawn | Piece::BlackPawn => self.get_pawn_attack(square, color),
+                    Piece::WhiteKnight | Piece::BlackKnight => self.get_knight_attack(square),
+                    Piece::WhiteKing | Piece::BlackKing => self.get_king_attack(square),
+                    Piece::WhiteBishop | Piece::BlackBishop => {
+                        self.get_bishop_attack(square, occupancy)
+                    }
+                    Piece::WhiteRook | Piece::BlackRook => self.get_rook_attack(square, occupancy),
+                    _ => self.get_queen_attack(square, occupancy),
+                };
+            }
+        }
+
+        attacks
+    }
+
+    // Squares strictly between two aligned squares, empty if they are not on one line
+    pub fn between(&self, a: Square, b: Square) -> BitBoard {
+        self.between[a as usize][b as usize]
+    }
+
+    // Whole line through two aligned squares including both of them, empty if they are not on one line
+    pub fn line(&self, a: Square, b: Square) -> BitBoard {
+        self.line[a as usize][b as usize]
+    }
+
+    // Opponent pieces giving check to the king of the side to move
+    pub fn checkers(&self, board: &Board) -> BitBoard {
+        let king_square = self.king_square(board, board.active_color());
+
+        self.attackers_to(board, king_square, board.get_occupancies(Color::Both))
+            & board.get_occupancies(board.opponent_color())
+    }
+
+    // Pieces of the given color that can not leave the line between their king and an enemy slider
+    pub fn pinned(&self, board: &Board, color: Color) -> BitBoard {
+        self.king_blockers(board, color, color.opposite()) & board.get_occupancies(color)
+    }
+
+    /*
+     * Pieces of any color that are the only piece between the king of 'king_color'
+     * and a slider of 'slider_color'. Own pieces are pinned, opponent pieces give discovered check
+     * when they move off the line.
+     */
+    pub fn king_blockers(&self, board: &Board, king_color: Color, slider_color: Color) -> BitBoard {
+        let king_square = self.king_square(board, king_color);
+        let occupancy = board.get_occupancies(Color::Both);
+        let slider = |piece: Piece| board.bitboards[piece.to_color(slider_color) as usize];
+        let queens = slider(Piece::WhiteQueen);
+
+        // Sliders that would attack the king on an empty board
+        let mut snipers = (self.get_rook_attack(king_square, BitBoard::default())
+            & (slider(Piece::WhiteRook) | queens))
+            | (self.get_bishop_attack(king_square, BitBoard::default())
+                & (slider(Piece::WhiteBishop) | queens));
+        let mut blockers = BitBoard::default();
+
+        while !snipers.empty() {
+            let sniper_square = snipers.lsb_bit_square();
+            snipers.pop_bit_value(sniper_square);
+
+            let between = self.between[king_square as usize][sniper_square as usize] & occupancy;
+            if between.count_ones() == 1 {
+                blockers |= between;
+            }
+        }
+
+        blockers
+    }
+
+    pub fn king_square(&self, board: &Board, color: Color) -> Square {
+        board.bitboards[Piece::WhiteKing.to_color(color) as usize].lsb_bit_square()
+    }
+}
+/*
+ * Legal move generation.
+ * Checkers, pinned pieces and pin rays are computed once per position, so every generated move
+ * is legal and can be played with 'Board::make_legal_move' without checking the king afterwards.
+ */
+impl MoveGenerator {
+    pub fn generate_legal_moves(&self, board: &Board, move_list: &mut MoveList) {
+        let color = board.active_color();
+        let opponent_occupancy = board.get_occupancies(board.opponent_color());
+        let friendly_occupancy = board.get_occupancies(color);
+        let occupancy = board.get_occupancies(Color::Both);
+        let king = Piece::WhiteKing.to_color(color);
+        let king_square = self.king_square(board, color);
+        let checkers = self.checkers(board);
+
+        // King is taken off the board, otherwise it would hide from a slider behind itself
+        let without_king = occupancy & !king_square.get_bitboard();
+        let mut king_targets = self.get_king_attack(king_square) & !friendly_occupancy;
+        let mut safe_targets = BitBoard::default();
+
+        while !king_targets.empty() {
+            let target_square = king_targets.lsb_bit_square();
+            king_targets.pop_bit_value(target_square);
+
+            let attackers = self.attackers_to(board, target_square, without_king);
+            if (attackers & opponent_occupancy).empty() {
+                safe_targets.set_bit_value(target_square);
+            }
+        }
+
+        // Only king can escape double check
+        if checkers.count_ones() > 1 {
+            self.add_move(board, king, king_square, safe_targets, move_list);
+            return;
+        }
+
+        // Single check can also be answered by capturing the checker or blocking the line
+        let target_mask = if checkers.empty() {
+            !friendly_occupancy
+        } else {
+            let checker_square = checkers.lsb_bit_square();
+            self.between[king_square as usize][checker_square as usize] | checkers
+        };
+        let pinned = self.pinned(board, color);
+
+        self.generate_legal_pawn_moves(board, move_list, king_square, target_mask, pinned);
+
+        if checkers.empty() {
+            self.generate_castling_moves(board, move_list);
+        }
+
+        self.add_move(board, king, king_square, safe_targets, move_list);
+
+        for piece in [
+            Piece::WhiteQueen,
+            Piece::WhiteRook,
+            Piece::WhiteBishop,
+            Piece::WhiteKnight,
+        ] {
+            let piece = piece.to_color(color);
+            let mut piece_bitboard = board.bitboards[piece as usize];
+
+            while !piece_bitboard.empty() {
+                let source_square = piece_bitboard.lsb_bit_square();
+                piece_bitboard.pop_bit_value(source_square);
+
+                let attack = match piece {
+                    Piece::WhiteKnight | Piece::BlackKnight => {
+                        self.get_knight_attack(source_square)
+                    }
+                    Piece::WhiteRook | Piece::BlackRook => {
+                        self.get_rook_attack(source_square, occupancy)
+                    }
+                    Piece::WhiteBishop | Piece::BlackBishop => {
+                        self.get_bishop_attack(source_square, occupancy)
+                    }
+                    _ => self.get_queen_attack(source_square, occupancy),
+                };
+
+                let mut moves = attack & !friendly_occupancy & target_mask;
+                // Pinned piece may only move along the pin ray
+                if pinned.get_bit_value(source_square) {
+                    moves &= self.line[king_square as usize][source_square as usize];
+                }
+
+                self.add_move(board, piece, source_square, moves, move_list);
+            }
+        }
+    }
+
+    fn generate_legal_pawn_moves(
+        &self,
+        board: &Board,
+        move_list: &mut MoveList,
+        king_square: Square,
+        target_mask: BitBoard,
+        pinned: BitBoard,
+    ) {
+        let color = board.active_color();
+        let piece = Piece::WhitePawn.to_color(color);
+        let mut piece_board = board.bitboards[piece as usize];
+        let opponent_occupancy = board.get_occupancies(board.opponent_color());
+
+        while !piece_board.empty() {
+            let source_square = piece_board.lsb_bit_square();
+            piece_board.pop_bit_value(source_square);
+
+            let attacks = self.get_pawn_attack(source_square, color);
+            let mut moves = (self.get_pawn_pushes(board, source_square)
+                | (attacks & opponent_occupancy))
+                & target_mask;
+
+            if pinned.get_bit_value(source_square) {
+                moves &= self.line[king_square as usize][source_square as usize];
+            }
+
+            if let Some(en_passant_square) = board.game_state.en_passant_target {
+                if attacks.get_bit_value(en_passant_square)
+                    && self.is_legal_en_passant(
+                        board,
+                        king_square,
+                        source_square,
+                        en_passant_square,
+                    )
+                {
+                    moves.set_bit_value(en_passant_square);
+                }
+            }
+
+            self.add_move(board, piece, source_square, moves, move_list);
+        }
+    }
+
+    /*
+     * En-passant removes two pieces from the same rank, which can expose the king in a way
+     * pin detection does not see (both pawns between king and rook). Position after the capture
+     * is checked directly instead: no opponent piece, except the captured pawn, may attack the king.
+     */
+    pub(super) fn is_legal_en_passant(
+        &self,
+        board: &Board,
+        king_square: Square,
+        source_square: Square,
+        en_passant_square: Square,
+    ) -> bool {
+        let captured = Square::get_by_index(en_passant_square as u8 ^ 8).get_bitboard();
+        let occupancy =
+            (board.get_occupancies(Color::Both) & !source_square.get_bitboard() & !captured)
+                | en_passant_square.get_bitboard();
+
+        let attackers = self.attackers_to(board, king_square, occupancy)
+            & board.get_occupancies(board.opponent_color())
+            & !captured;
+
+        attackers.empty()
+    }
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board_repr::fen::Fen,
+        move_generation::{
+            moves::MoveType,
+            tables::{
+                generate_bishop_attack, generate_king_attack_masks, generate_knight_attack_masks,
+                generate_pawn_attack_masks, generate_rook_attack,
+            },
+        },
+        _EN_PASSANT, _POSITION_3, _POSITION_4, _POSITION_5, _POSITION_6, _START_FEN,
+        _TRICKY_POSITION,
+    };
+
+    use super::*;
+
+    const POSITIONS: [&str; 7] = [
+        _START_FEN,
+        _TRICKY_POSITION,
+        _POSITION_3,
+        _POSITION_4,
+        _POSITION_5,
+        _POSITION_6,
+        _EN_PASSANT,
+    ];
+
+    // Attack of the piece standing on the square, computed by the slow table generators
+    fn brute_attack(piece: Piece, square: Square, occupancy: BitBoard) -> BitBoard {
+        match piece {
+            Piece::WhitePawn | Piece::BlackPawn => {
+                generate_pawn_attack_masks()[piece.color() as usize][square as usize]
+            }
+            Piece::WhiteKnight | Piece::BlackKnight => {
+                generate_knight_attack_masks()[square as usize]
+            }
+            Piece::WhiteKing | Piece::BlackKing => generate_king_attack_masks()[square as usize],
+            Piece::WhiteBishop | Piece::BlackBishop => generate_bishop_attack(square, occupancy),
+            Piece::WhiteRook | Piece::BlackRook => generate_rook_attack(square, occupancy),
+            _ => {
+                generate_bishop_attack(square, occupancy) | generate_rook_attack(square, occupancy)
+            }
+        }
+    }
+
+    fn brute_attackers_to(board: &Board, target: Square, occupancy: BitBoard) -> BitBoard {
+        let mut attackers = BitBoard::default();
+        for square in Square::iter() {
+            let piece = board.piece_by_square[square as usize];
+            if !piece.is_none() && brute_attack(piece, square, occupancy).get_bit_value(target) {
+                attackers.set_bit_value(square);
+            }
+        }
+        attackers
+    }
+
+    // Own piece is pinned if taking it off the board uncovers a new attack on its king
+    fn brute_pinned(board: &Board, color: Color) -> BitBoard {
+        let king_square = MoveGenerator::shared().king_square(board, color);
+        let occupancy = board.get_occupancies(Color::Both);
+        let opponent = board.get_occupancies(color.opposite());
+        let attackers = brute_attackers_to(board, king_square, occupancy) & opponent;
+
+        let mut pinned = BitBoard::default();
+        for square in Square::iter() {
+            let piece = board.piece_by_square[square as usize];
+            if piece.is_none() || piece.color() != color || square == king_square {
+                continue;
+            }
+            let without_piece = occupancy & !square.get_bitboard();
+            if brute_attackers_to(board, king_square, without_piece) & opponent != attackers {
+                pinned.set_bit_value(square);
+            }
+        }
+        pinned
+    }
+
+    // Walks the whole tree comparing legal moves with pseudo legal moves that survive 'make_move'
+    fn assert_same_moves(board: &mut Board, mg: &MoveGenerator, depth: i8) {
+        let mut legal = MoveList::new();
+        mg.generate_legal_moves(board, &mut legal);
+        let mut pseudo_legal = MoveList::new();
+        mg.generate_moves(board, &mut pseudo_legal, MoveType::All);
+
+        let mut expected = vec![];
+        for move_data in pseudo_legal {
+            if board.make_move(move_data, mg) {
+                board.unmake_move();
+                expected.push(move_data.data());
+            }
+        }
+        let legal = &legal.moves[..legal.count as usize];
+        let mut actual: Vec<u32> = legal.iter().map(|m| m.data()).collect();
+        expected.sort();
+        actual.sort();
+        assert_eq!(actual, expected);
+
+        if depth > 1 {
+            for move_data in legal {
+                board.make_legal_move(*move_data);
+                assert_same_moves(board, mg, depth - 1);
+                board.unmake_move();
+            }
+        }
+    }
+
+    #[test]
+    fn test_legal_moves_match_pseudo_legal_filtering() {
+        let mg = MoveGenerator::shared();
+
+        for fen in POSITIONS {
+            assert_same_moves(&mut Fen::to_board(fen), mg, 3);
+        }
+    }
+
+    #[test]
+    fn test_en_passant_discovered_check() {
+        let mg = MoveGenerator::shared();
+        let mut move_list = MoveList::new();
+
+        // Capturing en-passant would remove both pawns between the king and the rook
+        let board = Fen::to_board("8/8/8/KPp4r/8/8/8/7k w - c6 0 2");
+        mg.generate_legal_moves(&board, &mut move_list);
+        assert!(move_list.into_iter().all(|m| !m.en_passant()));
+
+        // Checking pawn can be captured en-passant
+        let mut move_list = MoveList::new();
+        let board = Fen::to_board("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1");
+        mg.generate_legal_moves(&board, &mut move_list);
+        assert!(move_list.into_iter().any(|m| m.en_passant()));
+    }
+
+    #[test]
+    fn test_attack_queries_match_brute_force() {
+        let mg = MoveGenerator::shared();
+
+        for fen in POSITIONS.into_iter().chain([
+            // Pinned pieces of both colors and a checking knight
+            "4k3/4r3/8/1b6/8/3N4/4B3/r2PK2q w - - 0 1",
+            "3qk3/8/8/1B6/8/8/5n2/R3K2R w KQ - 0 1",
+        ]) {
+            let board = Fen::to_board(fen);
+            let occupancy = board.get_occupancies(Color::Both);
+
+            for square in Square::iter() {
+                assert!(
+                    mg.attackers_to(&board, square, occupancy)
+                        == brute_attackers_to(&board, square, occupancy)
+                );
+            }
+
+            for color in [Color::White, Color::Black] {
+                let mut attacks = BitBoard::default();
+                for square in Square::iter() {
+                    if !(brute_attackers_to(&board, square, occupancy)
+                        & board.get_occupancies(color))
+                    .empty()
+                    {
+                        attacks.set_bit_value(square);
+                    }
+                }
+                assert!(mg.attacks_by(&board, color) == attacks);
+                assert!(mg.pinned(&board, color) == brute_pinned(&board, color));
+            }
+
+            let king_square = mg.king_square(&board, board.active_color());
+            let checkers = brute_attackers_to(&board, king_square, occupancy)
+                & board.get_occupancies(board.opponent_color());
+            assert!(mg.checkers(&board) == checkers);
+        }
+    }
+
+    #[test]
+    fn test_geometry_tables_match_brute_force() {
+        let mg = MoveGenerator::shared();
+        let empty = BitBoard::default();
+
+        for a in Square::iter() {
+            for b in Square::iter() {
+                let (a_bb, b_bb) = (a.get_bitboard(), b.get_bitboard());
+                let mut between = BitBoard::default();
+                let mut line = BitBoard::default();
+
+                for slider in [generate_rook_attack, generate_bishop_attack] {
+                    if a != b && slider(a, empty).get_bit_value(b) {
+                        // Rays from both ends meet on the squares between them
+                        between = slider(a, b_bb) & slider(b, a_bb);
+                        // Other rays of the two squares are parallel or cross on 'a' or 'b'
+                        line = (slider(a, empty) & slider(b, empty)) | a_bb | b_bb;
+                    }
+                }
+
+                assert!(mg.between(a, b) == between);
+                assert!(mg.line(a, b) == line);
+            }
+        }
+    }
+}
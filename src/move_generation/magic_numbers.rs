@@ -0,0 +1,188 @@
+/*
+ * Magic numbers for slider attack lookups.
+ * Generated by 'chess_engine_rust magics --seed 24301', do not edit by hand.
+ *
+ * Index of the attack is ((blockers & mask) * magic) >> (64 - index bits) + square offset.
+ * Tables of different squares may overlap where they store the same attack or leave gaps.
+ */
+
+pub const ROOK_TABLE_SIZE: usize = 102_400;
+pub const BISHOP_TABLE_SIZE: usize = 5_248;
+
+pub const ROOK_MAGICS: [u64; 64] = [
+    36029364492845056u64,
+    72077524837861120u64,
+    2954378982637716736u64,
+    1333069956476057600u64,
+    324263571251265664u64,
+    10448430317516685314u64,
+    1188952500657455360u64,
+    9259401383665321216u64,
+    294000626671599616u64,
+    4899987038203285504u64,
+    144255994285785216u64,
+    583779136595575299u64,
+    144255959932339200u64,
+    54746900152320128u64,
+    1297318171954511876u64,
+    1154328903687405570u64,
+    22589466937917700u64,
+    297237850289602600u64,
+    141287512612867u64,
+    1441434455515334688u64,
+    1126449796907014u64,
+    36733034250109952u64,
+    2323998694984122880u64,
+    4611697013561533508u64,
+    1170936042702782594u64,
+    1152956691130613888u64,
+    3463268251424261120u64,
+    2738751669424111632u64,
+    1125934400815232u64,
+    3472838271246274568u64,
+    2451085213888414224u64,
+    2323861814359638177u64,
+    9457594676736430212u64,
+    143211565686784u64,
+    3323656943783907332u64,
+    757308493601458176u64,
+    2307109680977019906u64,
+    576570841039450176u64,
+    38423739569801281u64,
+    162901767749713u64,
+    70370891825184u64,
+    4652288921525305344u64,
+    72620821017919520u64,
+    70446055817224u64,
+    19140575442241536u64,
+    73183562798596608u64,
+    1765992730209288202u64,
+    2307550002307792900u64,
+    144151541761179904u64,
+    301811545927582080u64,
+    9242547523939221760u64,
+    730146159382891008u64,
+    1126449730289792u64,
+    6757598531453056u64,
+    3379384690017280u64,
+    9871903647414125056u64,
+    1155740802745385090u64,
+    2306124759076708377u64,
+    2373680953038999561u64,
+    99114444919209993u64,
+    89061600002197u64,
+    281784281466409u64,
+    4505803215609988u64,
+    9223372178660264066u64,
+];
+
+pub const ROOK_INDEX_BITS: [u8; 64] = [
+    12, 11, 11, 11, 11, 11, 11, 12,
+    11, 10, 10, 10, 10, 10, 10, 11,
+    11, 10, 10, 10, 10, 10, 10, 11,
+    11, 10, 10, 10, 10, 10, 10, 11,
+    11, 10, 10, 10, 10, 10, 10, 11,
+    11, 10, 10, 10, 10, 10, 10, 11,
+    11, 10, 10, 10, 10, 10, 10, 11,
+    12, 11, 11, 11, 11, 11, 11, 12,
+];
+
+pub const ROOK_OFFSETS: [u32; 64] = [
+    0, 4096, 6144, 8192, 10240, 12288, 14336, 16384,
+    20480, 22528, 23552, 24576, 25600, 26624, 27648, 28672,
+    30720, 32768, 33792, 34816, 35840, 36864, 37888, 38912,
+    40960, 43008, 44032, 45056, 46080, 47104, 48128, 49152,
+    51200, 53248, 54272, 55296, 56320, 57344, 58368, 59392,
+    61440, 63488, 64512, 65536, 66560, 67584, 68608, 69632,
+    71680, 73728, 74752, 75776, 76800, 77824, 78848, 79872,
+    81920, 86016, 88064, 90112, 92160, 94208, 96256, 98304,
+];
+
+pub const BISHOP_MAGICS: [u64; 64] = [
+    83334256436973696u64,
+    4689461116452085760u64,
+    4904420553093284225u64,
+    4789474806727824u64,
+    2315415427255107780u64,
+    1189236186476972549u64,
+    8070597935582150720u64,
+    577324972756701256u64,
+    9224503177025948928u64,
+    72137317288247330u64,
+    72075191596941825u64,
+    2338643247680u64,
+    638061419954376u64,
+    22852834932359236u64,
+    1152998472910145544u64,
+    1126039561439234u64,
+    2251940357477504u64,
+    6057482373757534336u64,
+    578730711373062152u64,
+    4615068289134037009u64,
+    40550272334823432u64,
+    162692538820739616u64,
+    7494271256733294673u64,
+    11817524898446970886u64,
+    1143492629893138u64,
+    2909694866593288705u64,
+    1302668391257080832u64,
+    22522396455997472u64,
+    2307251483643494402u64,
+    9223937185850495015u64,
+    565157575524675u64,
+    577745119407768064u64,
+    1191207324187902720u64,
+    4616472231208290309u64,
+    2594095444327007360u64,
+    297241990658523424u64,
+    13513006495309833u64,
+    5316517094031106180u64,
+    576751300667113984u64,
+    9511925705932286976u64,
+    1298180193633962240u64,
+    38844663511457922u64,
+    848823547102212u64,
+    142405141505u64,
+    1201218902821888u64,
+    2621130177303806340u64,
+    1134756670784512u64,
+    148689715069976641u64,
+    9079837084372512u64,
+    1153559599442821632u64,
+    1225051748092616704u64,
+    72057732584312114u64,
+    4647996702774296592u64,
+    40536865056621074u64,
+    1767135122554917124u64,
+    578730231280451603u64,
+    17884279476305u64,
+    9224010871129375241u64,
+    9011601797612611u64,
+    580546443086912u64,
+    2252074968547840u64,
+    1165906773983887488u64,
+    9223381726317904912u64,
+    4729360168192593094u64,
+];
+
+pub const BISHOP_INDEX_BITS: [u8; 64] = [
+    6, 5, 5, 5, 5, 5, 5, 6,
+    5, 5, 5, 5, 5, 5, 5, 5,
+    5, 5, 7, 7, 7, 7, 5, 5,
+    5, 5, 7, 9, 9, 7, 5, 5,
+    5, 5, 7, 9, 9, 7, 5, 5,
+    5, 5, 7, 7, 7, 7, 5, 5,
+    5, 5, 5, 5, 5, 5, 5, 5,
+    6, 5, 5, 5, 5, 5, 5, 6,
+];
+
+pub const BISHOP_OFFSETS: [u32; 64] = [
+    0, 64, 96, 128, 160, 192, 224, 256,
+    320, 352, 384, 416, 448, 480, 512, 544,
+    576, 608, 640, 768, 896, 1024, 1152, 1184,
+    1216, 1248, 1280, 1408, 1920, 2432, 2560, 2592,
+    2624, 2656, 2688, 2816, 3328, 3840, 3968, 4000,
+    4032, 4064, 4096, 4224, 4352, 4480, 4608, 4640,
+    4672, 4704, 4736, 4768, 4800, 4832, 4864, 4896,
+    4928, 4992, 5024, 5056, 5088, 5120, 5152, 5184,
+];
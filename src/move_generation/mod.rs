@@ -1,7 +1,14 @@
+pub mod checks;
 mod displays;
 pub mod generator;
 pub mod init;
+pub mod legal;
+#[rustfmt::skip]
+pub mod magic_numbers;
 pub mod magics;
 pub mod move_list;
 pub mod moves;
+pub mod pext;
+pub mod san;
+pub mod static_tables;
 pub mod tables;
@@ -2,7 +2,7 @@ use crate::board_repr::piece::Piece;
 
 use super::moves::Move;
 
-const MAX_LEGAL_MOVES: usize = 255;
+pub const MAX_LEGAL_MOVES: usize = 255;
 
 pub struct MoveList {
     pub moves: [Move; MAX_LEGAL_MOVES],
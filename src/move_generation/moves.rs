@@ -2,9 +2,16 @@ use crate::board_repr::{piece::Piece, square::Square};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum MoveType {
-    Quite,
+    // Moves to empty squares: pawn pushes (including push promotions), castling and piece moves
+    Quiet,
+    // Moves onto opponent pieces, including en-passant and capture promotions
     Capture,
+    // Quiet and capture moves together
     All,
+    // Legal replies to a check, nothing is generated when not in check
+    Evasions,
+    // Non-capturing, non-promoting moves that give check
+    QuietChecks,
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -79,6 +86,14 @@ impl Move {
     pub fn double_push(&self) -> bool {
         self.data & 0x4000000 > 0
     }
+
+    pub fn data(&self) -> u32 {
+        self.data
+    }
+
+    pub fn from_data(data: u32) -> Self {
+        Self { data }
+    }
 }
 
 impl Move {
@@ -87,7 +102,7 @@ impl Move {
             "{}{}{}",
             &self.source_square().to_string().to_lowercase(),
             &self.target_square().to_string().to_lowercase(),
-            self.promoted_piece().to_fen_string(),
+            self.promoted_piece().to_fen_string().to_lowercase(),
         )
     }
 }
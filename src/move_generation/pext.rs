@@ -0,0 +1,148 @@
+/*
+ * PEXT slider attack backend.
+ * On x86-64 CPUs with BMI2 '_pext_u64' gathers relevant blocker bits into a dense index in one
+ * instruction, so no magic multiplication is needed and tables have no collisions at all.
+ * Backend is picked at runtime, magic lookup stays as the fallback for every other CPU.
+ */
+
+use crate::board_repr::{bit_board::BitBoard, square::Square};
+
+use super::generator::MoveGenerator;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliderBackend {
+    Magic,
+    Pext,
+}
+
+impl SliderBackend {
+    // Fastest backend supported by the CPU we are running on
+    pub fn detect() -> Self {
+        if Self::pext_supported() {
+            SliderBackend::Pext
+        } else {
+            SliderBackend::Magic
+        }
+    }
+
+    pub fn pext_supported() -> bool {
+        #[cfg(target_arch = "x86_64")]
+        {
+            is_x86_feature_detected!("bmi2")
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        {
+            false
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "bmi2")]
+fn pext_bmi2(value: u64, mask: u64) -> u64 {
+    std::arch::x86_64::_pext_u64(value, mask)
+}
+
+/*
+ * Index of the blocker set inside the square's PEXT table.
+ *
+ * # Safety
+ * The CPU must support BMI2 ('SliderBackend::pext_supported()' returned true).
+ */
+#[inline(always)]
+unsafe fn pext_index(blocker: BitBoard, mask: BitBoard) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        pext_bmi2(blocker.value(), mask.value()) as usize
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = (blocker, mask);
+        unreachable!("PEXT backend is only available on x86-64")
+    }
+}
+
+/*
+ * PEXT lookups of the generator.
+ * Generator only has 'SliderBackend::Pext' when 'MoveGenerator::with_backend' or
+ * 'MoveGenerator::shared' detected BMI2, the field can not be set outside of move generation.
+ */
+impl MoveGenerator {
+    #[inline(always)]
+    pub(super) fn pext_bishop_attack(&self, square: Square, blocker: BitBoard) -> BitBoard {
+        debug_assert!(self.slider_backend == SliderBackend::Pext);
+        let mask = self.bishop_magics[square as usize].mask;
+        // SAFETY: generator with PEXT backend exists only on CPUs with BMI2
+        let index =
+            self.bishop_pext_offsets[square as usize] + unsafe { pext_index(blocker, mask) };
+        self.bishop_pext[index]
+    }
+
+    #[inline(always)]
+    pub(super) fn pext_rook_attack(&self, square: Square, blocker: BitBoard) -> BitBoard {
+        debug_assert!(self.slider_backend == SliderBackend::Pext);
+        let mask = self.rook_magics[square as usize].mask;
+        // SAFETY: generator with PEXT backend exists only on CPUs with BMI2
+        let index = self.rook_pext_offsets[square as usize] + unsafe { pext_index(blocker, mask) };
+        self.rook_pext[index]
+    }
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use strum::IntoEnumIterator;
+
+    use crate::{
+        board_repr::square::Square,
+        move_generation::{
+            generator::MoveGenerator,
+            tables::{
+                generate_blockers, get_bishop_relevant_occupancy_mask,
+                get_rook_relevant_occupancy_mask,
+            },
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_pext_and_magic_backends_are_identical() {
+        if !SliderBackend::pext_supported() {
+            println!("BMI2 is not supported, PEXT backend is not tested");
+            return;
+        }
+
+        let magic = MoveGenerator::with_backend(SliderBackend::Magic);
+        let pext = MoveGenerator::with_backend(SliderBackend::Pext);
+
+        for square in Square::iter() {
+            for blocker in generate_blockers(get_rook_relevant_occupancy_mask(square)) {
+                assert!(
+                    magic.get_rook_attack(square, blocker) == pext.get_rook_attack(square, blocker)
+                );
+            }
+            for blocker in generate_blockers(get_bishop_relevant_occupancy_mask(square)) {
+                assert!(
+                    magic.get_bishop_attack(square, blocker)
+                        == pext.get_bishop_attack(square, blocker)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_shared_generator_uses_detected_backend() {
+        assert_eq!(
+            MoveGenerator::shared().slider_backend(),
+            SliderBackend::detect()
+        );
+        // PEXT request falls back to magics on CPUs without BMI2
+        assert_eq!(
+            MoveGenerator::with_backend(SliderBackend::Pext).slider_backend(),
+            SliderBackend::detect()
+        );
+    }
+}
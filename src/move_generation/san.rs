@@ -0,0 +1,335 @@
+/*
+ * Standard Algebraic Notation.
+ * Disambiguation is computed from the legal moves of the position and check or mate suffix
+ * from the position after the move, so formatting needs the board the move is played on.
+ */
+
+use std::str::FromStr;
+
+use crate::board_repr::{board::Board, square::Square};
+
+use super::{generator::MoveGenerator, move_list::MoveList, moves::Move};
+
+#[derive(Debug)]
+pub struct San;
+
+impl San {
+    // Formats a legal move of the side to move, e.g. 'Nbd7', 'exd6', 'O-O-O', 'e8=Q+', 'Qxf7#'
+    pub fn from_move(move_data: Move, board: &Board, mg: &MoveGenerator) -> String {
+        let mut san = Self::move_text(move_data, board, mg);
+
+        let mut board = board.clone();
+        board.make_legal_move(move_data);
+        if !mg.checkers(&board).empty() {
+            let mut replies = MoveList::new();
+            mg.generate_legal_moves(&board, &mut replies);
+            san.push(if replies.count == 0 { '#' } else { '+' });
+        }
+
+        san
+    }
+
+    fn move_text(move_data: Move, board: &Board, mg: &MoveGenerator) -> String {
+        let source_square = move_data.source_square();
+        let target_square = move_data.target_square();
+
+        if move_data.castling() {
+            return match target_square.file() {
+                6 => String::from("O-O"),
+                _ => String::from("O-O-O"),
+            };
+        }
+
+        let piece = move_data.piece();
+        let is_capture = !move_data.captured_piece().is_none() || move_data.en_passant();
+        let mut san = String::new();
+
+        if piece.is_pawn() {
+            if is_capture {
+                san.push(file_char(source_square));
+            }
+        } else {
+            san.push_str(&piece.to_fen_string().to_uppercase());
+
+            // Other pieces of the same kind that can also go to the target square
+            let mut legal_moves = MoveList::new();
+            mg.generate_legal_moves(board, &mut legal_moves);
+            let rivals: Vec<Square> = legal_moves
+                .into_iter()
+                .filter(|m| {
+                    m.piece() == piece
+                        && m.target_square() == target_square
+                        && m.source_square() != source_square
+                })
+                .map(|m| m.source_square())
+                .collect();
+
+            if !rivals.is_empty() {
+                let same_file = rivals.iter().any(|s| s.file() == source_square.file());
+                let same_rank = rivals.iter().any(|s| s.rank() == source_square.rank());
+
+                if !same_file {
+                    san.push(file_char(source_square));
+                } else if !same_rank {
+                    san.push(rank_char(source_square));
+                } else {
+                    san.push(file_char(source_square));
+                    san.push(rank_char(source_square));
+                }
+            }
+        }
+
+        if is_capture {
+            san.push('x');
+        }
+        san.push_str(&target_square.to_string().to_lowercase());
+
+        if !move_data.promoted_piece().is_none() {
+            san.push('=');
+            san.push_str(&move_data.promoted_piece().to_fen_string().to_uppercase());
+        }
+
+        san
+    }
+
+    /*
+     * Resolves SAN to a legal move of the side to move.
+     * Parser is forgiving: check, mate and annotation marks, 'e.p.', missing or extra 'x', '-' and '='
+     * are ignored, castling may be written with zeros, piece letters may be lowercase, promotion
+     * piece defaults to queen, and plain UCI coordinates ('e2e4', 'e7e8q') are accepted too.
+     * Lowercase 'b' is read as the pawn file first and as a bishop only if no pawn move fits.
+     */
+    pub fn parse_move(san: &str, board: &Board, mg: &MoveGenerator) -> Option<Move> {
+        let mut legal_moves = MoveList::new();
+        mg.generate_legal_moves(board, &mut legal_moves);
+        let legal_moves: Vec<Move> = legal_moves.into_iter().collect();
+
+        let text = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        let text = text.strip_suffix("e.p.").unwrap_or(text).trim();
+        if text.is_empty() || !text.is_ascii() {
+            return None;
+        }
+
+        // Castling
+        let castling = text.replace('0', "O").to_uppercase();
+        if castling == "O-O" || castling == "O-O-O" {
+            let target_file = if castling == "O-O" { 6 } else { 2 };
+            return legal_moves
+                .into_iter()
+                .find(|m| m.castling() && m.target_square().file() == target_file);
+        }
+
+        let mut chars: Vec<char> = text
+            .chars()
+            .filter(|c| !matches!(c, 'x' | 'X' | ':' | '-' | '='))
+            .collect();
+
+        // Promotion piece follows the target rank
+        let mut promoted_piece = None;
+        if chars.len() > 2 && chars[chars.len() - 2].is_ascii_digit() {
+            let piece = chars.pop().unwrap().to_ascii_uppercase();
+            promoted_piece = Some(match piece {
+                'Q' | 'R' | 'B' | 'N' => piece,
+                _ => return None,
+            });
+        }
+
+        if chars.len() < 2 {
+            return None;
+        }
+        let target: String = chars.split_off(chars.len() - 2).into_iter().collect();
+        let target_square = Square::from_str(&target.to_uppercase()).ok()?;
+
+        // Moving piece, no piece letter means a pawn unless the full source square is given
+        let mut piece_char = None;
+        if let Some(first) = chars.first() {
+            if matches!(first.to_ascii_uppercase(), 'K' | 'Q' | 'R' | 'N' | 'P') || *first == 'B' {
+                piece_char = Some(first.to_ascii_uppercase());
+                chars.remove(0);
+            }
+        }
+
+        let moves = Self::find_moves(
+            &legal_moves,
+            target_square,
+            piece_char,
+            &chars,
+            promoted_piece,
+        )?;
+        if moves.len() != 1 && piece_char.is_none() && chars.first() == Some(&'b') {
+            let bishop_moves = Self::find_moves(
+                &legal_moves,
+                target_square,
+                Some('B'),
+                &chars[1..],
+                promoted_piece,
+            )?;
+            if let [move_data] = bishop_moves.as_slice() {
+                return Some(*move_data);
+            }
+        }
+
+        match moves.as_slice() {
+            [move_data] => Some(*move_data),
+            _ => None,
+        }
+    }
+
+    // Legal moves to the target square, 'disambiguation' is a source file, rank or full source square
+    fn find_moves(
+        legal_moves: &[Move],
+        target_square: Square,
+        mut piece_char: Option<char>,
+        disambiguation: &[char],
+        promoted_piece: Option<char>,
+    ) -> Option<Vec<Move>> {
+        let mut source_file = None;
+        let mut source_rank = None;
+        for c in disambiguation {
+            match c {
+                'a'..='h' => source_file = Some(*c as u8 - b'a'),
+                '1'..='8' => source_rank = Some(b'8' - *c as u8),
+                _ => return None,
+            }
+        }
+        if piece_char.is_none() && (source_file.is_none() || source_rank.is_none()) {
+            piece_char = Some('P');
+        }
+
+        let candidates = legal_moves
+            .iter()
+            .copied()
+            .filter(|m| {
+                let source_square = m.source_square();
+                let promoted = m.promoted_piece();
+
+                m.target_square() == target_square
+                    && piece_char
+                        .is_none_or(|p| m.piece().to_fen_string().to_uppercase() == p.to_string())
+                    && source_file.is_none_or(|file| source_square.file() == file)
+                    && source_rank.is_none_or(|rank| source_square.rank() == rank)
+                    && match promoted_piece {
+                        Some(p) => promoted.to_fen_string().to_uppercase() == p.to_string(),
+                        None => promoted.is_none() || promoted.is_queen(),
+                    }
+            })
+            .collect();
+
+        Some(candidates)
+    }
+}
+
+fn file_char(square: Square) -> char {
+    (b'a' + square.file()) as char
+}
+
+// Ranks are counted from the 8th rank
+fn rank_char(square: Square) -> char {
+    (b'8' - square.rank()) as char
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board_repr::fen::Fen, _POSITION_3, _POSITION_4, _POSITION_5, _START_FEN, _TRICKY_POSITION,
+    };
+
+    use super::*;
+
+    fn san_of(fen: &str, uci: &str) -> String {
+        let mg = MoveGenerator::shared();
+        let board = Fen::to_board(fen);
+        let mut move_list = MoveList::new();
+        mg.generate_legal_moves(&board, &mut move_list);
+        let move_data = move_list
+            .into_iter()
+            .find(|m| m.to_uci_string() == uci)
+            .unwrap();
+
+        San::from_move(move_data, &board, mg)
+    }
+
+    #[test]
+    fn test_format_san() {
+        assert_eq!(san_of(_START_FEN, "g1f3"), "Nf3");
+        assert_eq!(san_of(_START_FEN, "e2e4"), "e4");
+        assert_eq!(san_of(_TRICKY_POSITION, "e1c1"), "O-O-O");
+        assert_eq!(san_of(_TRICKY_POSITION, "e1g1"), "O-O");
+        assert_eq!(san_of(_TRICKY_POSITION, "e2a6"), "Bxa6");
+        // Both knights reach c3, file tells them apart
+        assert_eq!(san_of("4k3/8/8/8/8/8/8/1N1NK3 w - - 0 1", "b1c3"), "Nbc3");
+        assert_eq!(san_of("r3k3/8/8/8/8/8/5K2/r7 b - - 0 1", "a8a4"), "R8a4");
+        assert_eq!(san_of("4k3/8/8/8/8/Q1Q5/8/Q3K3 w - - 0 1", "a3b2"), "Qa3b2");
+        // En-passant is written as a plain capture
+        assert_eq!(san_of("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2", "e5d6"), "exd6");
+        assert_eq!(san_of("8/4P1k1/8/8/8/8/8/4K3 w - - 0 1", "e7e8q"), "e8=Q");
+        assert_eq!(san_of("6k1/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8r"), "e8=R+");
+        assert_eq!(san_of("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8"), "Ra8#");
+    }
+
+    #[test]
+    fn test_parse_forgiving_san() {
+        let mg = MoveGenerator::shared();
+        let board = Fen::to_board(_TRICKY_POSITION);
+        let parse = |san| San::parse_move(san, &board, mg).map(|m| m.to_uci_string());
+
+        assert_eq!(parse("Bxa6"), Some(String::from("e2a6")));
+        assert_eq!(parse("Ba6"), Some(String::from("e2a6")));
+        assert_eq!(parse("B:a6!?"), Some(String::from("e2a6")));
+        assert_eq!(parse("0-0-0"), Some(String::from("e1c1")));
+        assert_eq!(parse("o-o"), Some(String::from("e1g1")));
+        assert_eq!(parse("Nxe5"), None);
+        assert_eq!(parse("e2a6"), Some(String::from("e2a6")));
+        assert_eq!(parse("Be2-a6"), Some(String::from("e2a6")));
+        assert_eq!(parse("Rb1"), Some(String::from("a1b1")));
+        assert_eq!(parse("Qxf6+"), Some(String::from("f3f6")));
+        assert_eq!(parse("Kd1"), Some(String::from("e1d1")));
+        assert_eq!(parse("Zz9"), None);
+        // Lowercase piece letters, 'b' is a bishop when no b-pawn move fits
+        assert_eq!(parse("nxd7"), Some(String::from("e5d7")));
+        assert_eq!(parse("qxf6"), Some(String::from("f3f6")));
+        assert_eq!(parse("kd1"), Some(String::from("e1d1")));
+        assert_eq!(parse("bxa6"), Some(String::from("e2a6")));
+
+        // Both b-pawn and bishop take on c3, lowercase 'b' is the file then
+        let board = Fen::to_board("4k3/8/8/8/8/2n5/1P1B4/4K3 w - - 0 1");
+        let parse = |san| San::parse_move(san, &board, mg).map(|m| m.to_uci_string());
+        assert_eq!(parse("bxc3"), Some(String::from("b2c3")));
+        assert_eq!(parse("Bxc3"), Some(String::from("d2c3")));
+        assert_eq!(parse("b4"), Some(String::from("b2b4")));
+        assert_eq!(parse("be3"), Some(String::from("d2e3")));
+
+        let board = Fen::to_board("8/4P1k1/8/8/8/8/8/4K3 w - - 0 1");
+        let parse = |san| San::parse_move(san, &board, mg).map(|m| m.to_uci_string());
+        assert_eq!(parse("e8=N"), Some(String::from("e7e8n")));
+        assert_eq!(parse("e8n"), Some(String::from("e7e8n")));
+        assert_eq!(parse("e8"), Some(String::from("e7e8q")));
+        assert_eq!(parse("e7e8r"), Some(String::from("e7e8r")));
+    }
+
+    #[test]
+    fn test_san_round_trip() {
+        let mg = MoveGenerator::shared();
+
+        for fen in [
+            _START_FEN,
+            _TRICKY_POSITION,
+            _POSITION_3,
+            _POSITION_4,
+            _POSITION_5,
+        ] {
+            let board = Fen::to_board(fen);
+            let mut move_list = MoveList::new();
+            mg.generate_legal_moves(&board, &mut move_list);
+
+            for move_data in move_list {
+                let san = San::from_move(move_data, &board, mg);
+                let parsed = San::parse_move(&san, &board, mg).unwrap();
+                assert!(parsed == move_data, "{san}");
+            }
+        }
+    }
+}
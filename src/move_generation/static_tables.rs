@@ -0,0 +1,365 @@
+/*
+ * Attack tables evaluated at compile time.
+ * Functions here are const versions of the generators from 'tables.rs', so the tables are baked
+ * into the binary as statics and nothing has to be computed at startup.
+ * Runtime generators are kept as the reference implementation, tests check that both are identical.
+ */
+
+use crate::board_repr::bit_board::BitBoard;
+
+use super::magics::{
+    Magic, BISHOP_INDEX_BITS, BISHOP_MAGICS, BISHOP_OFFSETS, BISHOP_TABLE_SIZE, ROOK_INDEX_BITS,
+    ROOK_MAGICS, ROOK_OFFSETS, ROOK_TABLE_SIZE,
+};
+
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(-1, 1), (-1, -1), (1, 1), (1, -1)];
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+const KING_OFFSETS: [(i8, i8); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+pub static PAWN_ATTACKS: [[BitBoard; 64]; 2] = [
+    leaper_attacks(&[(-1, -1), (-1, 1)]),
+    leaper_attacks(&[(1, -1), (1, 1)]),
+];
+pub static KNIGHT_ATTACKS: [BitBoard; 64] = leaper_attacks(&KNIGHT_OFFSETS);
+pub static KING_ATTACKS: [BitBoard; 64] = leaper_attacks(&KING_OFFSETS);
+
+pub static ROOK_MAGIC_TABLE: [Magic; 64] = magics(
+    &ROOK_DIRECTIONS,
+    &ROOK_MAGICS,
+    &ROOK_INDEX_BITS,
+    &ROOK_OFFSETS,
+);
+pub static BISHOP_MAGIC_TABLE: [Magic; 64] = magics(
+    &BISHOP_DIRECTIONS,
+    &BISHOP_MAGICS,
+    &BISHOP_INDEX_BITS,
+    &BISHOP_OFFSETS,
+);
+
+// Filling the rook table takes a few million steps, which is more than const evaluation expects
+#[allow(long_running_const_eval)]
+pub static ROOK_ATTACKS: [BitBoard; ROOK_TABLE_SIZE] = slider_attacks(
+    &ROOK_DIRECTIONS,
+    &ROOK_MAGICS,
+    &ROOK_INDEX_BITS,
+    &ROOK_OFFSETS,
+);
+pub static BISHOP_ATTACKS: [BitBoard; BISHOP_TABLE_SIZE] = slider_attacks(
+    &BISHOP_DIRECTIONS,
+    &BISHOP_MAGICS,
+    &BISHOP_INDEX_BITS,
+    &BISHOP_OFFSETS,
+);
+
+// PEXT tables always take 2^(relevant occupancy bits) entries per square, no matter what magics are
+pub const ROOK_PEXT_TABLE_SIZE: usize = 102_400;
+pub const BISHOP_PEXT_TABLE_SIZE: usize = 5_248;
+
+pub static ROOK_PEXT_OFFSETS: [usize; 64] = pext_offsets(&ROOK_DIRECTIONS);
+pub static BISHOP_PEXT_OFFSETS: [usize; 64] = pext_offsets(&BISHOP_DIRECTIONS);
+
+#[allow(long_running_const_eval)]
+pub static ROOK_PEXT_ATTACKS: [BitBoard; ROOK_PEXT_TABLE_SIZE] = pext_attacks(&ROOK_DIRECTIONS);
+pub static BISHOP_PEXT_ATTACKS: [BitBoard; BISHOP_PEXT_TABLE_SIZE] =
+    pext_attacks(&BISHOP_DIRECTIONS);
+
+// Squares strictly between two squares on the same rank, file or diagonal, empty otherwise
+pub static BETWEEN: [[BitBoard; 64]; 64] = geometry_table(false);
+// Whole line (edge to edge) through two squares on the same rank, file or diagonal, empty otherwise
+pub static LINE: [[BitBoard; 64]; 64] = geometry_table(true);
+
+// Returns square index if (rank, file) is on the board. Rank 0 is the 8th rank, as in 'Square'.
+const fn square_at(rank: i8, file: i8) -> Option<u32> {
+    if rank < 0 || rank > 7 || file < 0 || file > 7 {
+        None
+    } else {
+        Some((rank * 8 + file) as u32)
+    }
+}
+
+const fn leaper_attacks(offsets: &[(i8, i8)]) -> [BitBoard; 64] {
+    let mut attacks = [BitBoard::from(0); 64];
+
+    let mut square = 0;
+    while square < 64 {
+        let (rank, file) = ((square / 8) as i8, (square % 8) as i8);
+        let mut attack = 0u64;
+
+        let mut i = 0;
+        while i < offsets.len() {
+            if let Some(target) = square_at(rank + offsets[i].0, file + offsets[i].1) {
+                attack |= 1 << target;
+            }
+            i += 1;
+        }
+
+        attacks[square] = BitBoard::from(attack);
+        square += 1;
+    }
+
+    attacks
+}
+
+// Walks every ray until the first blocker, blocker square itself is attacked
+const fn slider_attack(square: usize, directions: &[(i8, i8); 4], blocker: u64) -> u64 {
+    let (rank, file) = ((square / 8) as i8, (square % 8) as i8);
+    let mut attack = 0u64;
+
+    let mut i = 0;
+    while i < 4 {
+        let (dr, df) = directions[i];
+        let (mut r, mut f) = (rank + dr, file + df);
+
+        while let Some(target) = square_at(r, f) {
+            attack |= 1 << target;
+            if blocker & (1 << target) != 0 {
+                break;
+            }
+            r += dr;
+            f += df;
+        }
+        i += 1;
+    }
+
+    attack
+}
+
+// Squares that can hold a blocker: the last square of every ray is attacked whether occupied or not
+const fn relevant_occupancy_mask(square: usize, directions: &[(i8, i8); 4]) -> u64 {
+    let (rank, file) = ((square / 8) as i8, (square % 8) as i8);
+    let mut mask = 0u64;
+
+    let mut i = 0;
+    while i < 4 {
+        let (dr, df) = directions[i];
+        let (mut r, mut f) = (rank + dr, file + df);
+
+        while let (Some(target), Some(_)) = (square_at(r, f), square_at(r + dr, f + df)) {
+            mask |= 1 << target;
+            r += dr;
+            f += df;
+        }
+        i += 1;
+    }
+
+    mask
+}
+
+const fn magics(
+    directions: &[(i8, i8); 4],
+    magic_numbers: &[u64; 64],
+    index_bits: &[u8; 64],
+    offsets: &[u32; 64],
+) -> [Magic; 64] {
+    let mut magics = [Magic {
+        magic_number: 0,
+        mask: BitBoard::from(0),
+        offset: 0,
+        shift: 0,
+    }; 64];
+
+    let mut square = 0;
+    while square < 64 {
+        let mask = relevant_occupancy_mask(square, directions);
+
+        magics[square] = Magic {
+            magic_number: magic_numbers[square],
+            mask: BitBoard::from(mask),
+            offset: offsets[square] as u64,
+            shift: 64 - index_bits[square],
+        };
+        square += 1;
+    }
+
+    magics
+}
+
+const fn slider_attacks<const N: usize>(
+    directions: &[(i8, i8); 4],
+    magic_numbers: &[u64; 64],
+    index_bits: &[u8; 64],
+    offsets: &[u32; 64],
+) -> [BitBoard; N] {
+    let magics = magics(directions, magic_numbers, index_bits, offsets);
+    let mut table = [BitBoard::from(0); N];
+
+    let mut square = 0;
+    while square < 64 {
+        let magic = magics[square];
+        let mask = magic.mask.value();
+
+        // Carry-Rippler, same enumeration of blocker subsets as 'generate_blockers'.
+        // Constructive collisions, also between overlapping squares, write the same attack,
+        // so entries are simply overwritten.
+        let mut blocker = 0u64;
+        loop {
+            table[magic.index(BitBoard::from(blocker))] =
+                BitBoard::from(slider_attack(square, directions, blocker));
+
+            blocker = blocker.wrapping_sub(mask) & mask;
+            if blocker == 0 {
+                break;
+            }
+        }
+        square += 1;
+    }
+
+    table
+}
+
+// Direction of the step from 'a' towards 'b' if they share a rank, file or diagonal
+const fn direction(a: usize, b: usize) -> Option<(i8, i8)> {
+    let (dr, df) = ((b / 8) as i8 - (a / 8) as i8, (b % 8) as i8 - (a % 8) as i8);
+
+    if a == b || (dr != 0 && df != 0 && dr.abs() != df.abs()) {
+        None
+    } else {
+        Some((dr.signum(), df.signum()))
+    }
+}
+
+const fn geometry_table(whole_line: bool) -> [[BitBoard; 64]; 64] {
+    let mut table = [[BitBoard::from(0); 64]; 64];
+
+    let mut a = 0;
+    while a < 64 {
+        let mut b = 0;
+        while b < 64 {
+            if let Some((dr, df)) = direction(a, b) {
+                let (rank, file) = ((a / 8) as i8, (a % 8) as i8);
+                let mut squares = 0u64;
+
+                if whole_line {
+                    // Walk both ways from 'a' to the edges of the board
+                    squares |= 1 << a;
+                    let mut sign = -1;
+                    while sign <= 1 {
+                        let (mut r, mut f) = (rank + sign * dr, file + sign * df);
+                        while let Some(target) = square_at(r, f) {
+                            squares |= 1 << target;
+                            r += sign * dr;
+                            f += sign * df;
+                        }
+                        sign += 2;
+                    }
+                } else {
+                    let (mut r, mut f) = (rank + dr, file + df);
+                    while let Some(target) = square_at(r, f) {
+                        if target as usize == b {
+                            break;
+                        }
+                        squares |= 1 << target;
+                        r += dr;
+                        f += df;
+                    }
+                }
+
+                table[a][b] = BitBoard::from(squares);
+            }
+            b += 1;
+        }
+        a += 1;
+    }
+
+    table
+}
+
+const fn pext_offsets(directions: &[(i8, i8); 4]) -> [usize; 64] {
+    let mut offsets = [0usize; 64];
+
+    let mut offset = 0;
+    let mut square = 0;
+    while square < 64 {
+        offsets[square] = offset;
+        offset += 1 << relevant_occupancy_mask(square, directions).count_ones();
+        square += 1;
+    }
+
+    offsets
+}
+
+const fn pext_attacks<const N: usize>(directions: &[(i8, i8); 4]) -> [BitBoard; N] {
+    let offsets = pext_offsets(directions);
+    let mut table = [BitBoard::from(0); N];
+
+    let mut square = 0;
+    while square < 64 {
+        let mask = relevant_occupancy_mask(square, directions);
+
+        // Carry-Rippler counts through subsets in the same order PEXT packs them,
+        // so i-th subset is exactly the one with PEXT index i
+        let mut blocker = 0u64;
+        let mut i = 0;
+        loop {
+            table[offsets[square] + i] = BitBoard::from(slider_attack(square, directions, blocker));
+
+            blocker = blocker.wrapping_sub(mask) & mask;
+            i += 1;
+            if blocker == 0 {
+                break;
+            }
+        }
+        square += 1;
+    }
+
+    table
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use crate::move_generation::tables::{
+        generate_bishop_attack_masks, generate_king_attack_masks, generate_knight_attack_masks,
+        generate_pawn_attack_masks, generate_rook_attack_masks,
+    };
+
+    use super::*;
+
+    fn assert_same_magics(static_magics: &[Magic; 64], runtime_magics: &[Magic; 64]) {
+        for (s, r) in static_magics.iter().zip(runtime_magics.iter()) {
+            assert_eq!(s.magic_number, r.magic_number);
+            assert!(s.mask == r.mask);
+            assert_eq!(s.offset, r.offset);
+            assert_eq!(s.shift, r.shift);
+        }
+    }
+
+    #[test]
+    fn test_leaper_tables_match_runtime_generation() {
+        assert!(PAWN_ATTACKS == generate_pawn_attack_masks());
+        assert!(KNIGHT_ATTACKS == generate_knight_attack_masks());
+        assert!(KING_ATTACKS == generate_king_attack_masks());
+    }
+
+    #[test]
+    fn test_slider_tables_match_runtime_generation() {
+        let (rook, rook_magics) = generate_rook_attack_masks();
+        let (bishop, bishop_magics) = generate_bishop_attack_masks();
+
+        assert!(ROOK_ATTACKS == *rook);
+        assert!(BISHOP_ATTACKS == *bishop);
+        assert_same_magics(&ROOK_MAGIC_TABLE, &rook_magics);
+        assert_same_magics(&BISHOP_MAGIC_TABLE, &bishop_magics);
+    }
+}
@@ -1,14 +1,16 @@
 /*
  * This module contains a number of function that are used to pregenerate attacks for each piece type.
- * These function are not called by the engine during the search, the only purpose of this is to pregenerate
- * attack bitboards/masks for pieces and later store them on the stack. Storing already computed values gives us huge
- * boost in performance.
+ * Engine itself uses tables computed at compile time by 'static_tables.rs'. These runtime generators
+ * are the reference implementation those tables are tested against, and are used by the magic finder.
  */
 
 use crate::board_repr::{bit_board::BitBoard, piece::Color, square::Square};
 use strum::IntoEnumIterator;
 
-use super::magics::{Magic, BISHOP_MAGICS, BISHOP_TABLE_SIZE, ROOK_MAGICS, ROOK_TABLE_SIZE};
+use super::magics::{
+    Magic, MagicPiece, BISHOP_INDEX_BITS, BISHOP_MAGICS, BISHOP_OFFSETS, BISHOP_TABLE_SIZE,
+    ROOK_INDEX_BITS, ROOK_MAGICS, ROOK_OFFSETS, ROOK_TABLE_SIZE,
+};
 
 //////////////////
 //    CONSTS    //
@@ -201,41 +203,21 @@ fn get_king_attack_mask(square: Square) -> BitBoard {
 //    GENERATE BISHOP TABLES    //
 //////////////////////////////////
 
-pub fn generate_bishop_attack_masks() -> ([BitBoard; BISHOP_TABLE_SIZE], [Magic; 64]) {
-    let mut offset = 0;
-    let mut table = [BitBoard::default(); BISHOP_TABLE_SIZE];
-    let mut magics_table = [Magic::default(); 64];
-    for square in Square::iter() {
-        let mask = get_bishop_relevant_occupancy_mask(square);
-        let bits = mask.count_ones();
-        let permutations = 2u64.pow(bits);
-        let shift = 64u8 - bits as u8;
-        let magic_number = BISHOP_MAGICS[square as usize];
-        let magic = Magic {
-            mask,
-            offset,
-            shift,
-            magic_number,
-        };
-
-        let blockers = generate_blockers(mask);
-        let attacks = generate_bishop_attacks(square, &blockers);
-
-        for i in 0..permutations {
-            let blocker_board = blockers[i as usize];
-            let index = magic.index(blocker_board);
-
-            if table[index] == BitBoard::default() {
-                table[index] = attacks[i as usize];
-                magics_table[square as usize] = magic;
-            } else {
-                panic!("Error while initializing magic piece attacks.")
-            }
-        }
-        offset += permutations;
-    }
-
-    (table, magics_table)
+pub fn generate_bishop_attack_masks() -> (Box<[BitBoard; BISHOP_TABLE_SIZE]>, [Magic; 64]) {
+    let (table, magics) = generate_slider_attack_table(
+        MagicPiece::BISHOP,
+        &BISHOP_MAGICS,
+        &BISHOP_INDEX_BITS,
+        &BISHOP_OFFSETS,
+    )
+    .expect("Error while initializing magic piece attacks.");
+
+    let table = table
+        .into_boxed_slice()
+        .try_into()
+        .expect("Attack table has wrong size");
+
+    (table, magics)
 }
 
 pub fn generate_bishop_attacks(square: Square, blockers: &[BitBoard]) -> Vec<BitBoard> {
@@ -358,41 +340,21 @@ pub fn get_bishop_relevant_occupancy_mask(square: Square) -> BitBoard {
 //    GENERATE ROOK TABLES      //
 //////////////////////////////////
 
-pub fn generate_rook_attack_masks() -> ([BitBoard; ROOK_TABLE_SIZE], [Magic; 64]) {
-    let mut offset = 0u64;
-    let mut table = [BitBoard::default(); ROOK_TABLE_SIZE];
-    let mut magics_table = [Magic::default(); 64];
-    for square in Square::iter() {
-        let mask = get_rook_relevant_occupancy_mask(square);
-        let bits = mask.count_ones();
-        let permutations = 2u64.pow(bits);
-        let shift = 64u8 - bits as u8;
-        let magic_number = ROOK_MAGICS[square as usize];
-        let magic = Magic {
-            mask,
-            offset,
-            shift,
-            magic_number,
-        };
-
-        let blockers = generate_blockers(mask);
-        let attacks = generate_rook_attacks(square, &blockers);
-
-        for i in 0..permutations {
-            let blocker_board = blockers[i as usize];
-            let index = magic.index(blocker_board);
-
-            if table[index] == BitBoard::default() {
-                table[index] = attacks[i as usize];
-                magics_table[square as usize] = magic;
-            } else {
-                panic!("Error while initializing magic piece attacks.")
-            }
-        }
-        offset += permutations;
-    }
-
-    (table, magics_table)
+pub fn generate_rook_attack_masks() -> (Box<[BitBoard; ROOK_TABLE_SIZE]>, [Magic; 64]) {
+    let (table, magics) = generate_slider_attack_table(
+        MagicPiece::ROOK,
+        &ROOK_MAGICS,
+        &ROOK_INDEX_BITS,
+        &ROOK_OFFSETS,
+    )
+    .expect("Error while initializing magic piece attacks.");
+
+    let table = table
+        .into_boxed_slice()
+        .try_into()
+        .expect("Attack table has wrong size");
+
+    (table, magics)
 }
 
 pub fn generate_rook_attacks(square: Square, blockers: &[BitBoard]) -> Vec<BitBoard> {
@@ -406,7 +368,7 @@ pub fn generate_rook_attacks(square: Square, blockers: &[BitBoard]) -> Vec<BitBo
     attacks
 }
 
-fn generate_rook_attack(square: Square, blocker: BitBoard) -> BitBoard {
+pub fn generate_rook_attack(square: Square, blocker: BitBoard) -> BitBoard {
     let mut occupancy = BitBoard::default();
     let tr = square.rank();
     let tf = square.file();
@@ -490,6 +452,73 @@ pub fn get_rook_relevant_occupancy_mask(square: Square) -> BitBoard {
     occupancy
 }
 
+//////////////////////////////////////
+//    GENERATE MAGIC SLIDER TABLES  //
+//////////////////////////////////////
+
+/*
+ * Builds attack table for all squares, square with N index bits uses 2^N entries from its offset.
+ * Index bits may be fewer than relevant occupancy bits, as long as blockers that share an index
+ * also share the attack (constructive collision). The same holds between squares, whose entries
+ * may overlap. Unused entries at the end are dropped, so table length is the size it really takes.
+ * Returns None if magic numbers produce a destructive collision.
+ */
+pub fn generate_slider_attack_table(
+    piece: MagicPiece,
+    magic_numbers: &[u64; 64],
+    index_bits: &[u8; 64],
+    offsets: &[u32; 64],
+) -> Option<(Vec<BitBoard>, [Magic; 64])> {
+    let table_size = offsets
+        .iter()
+        .zip(index_bits.iter())
+        .map(|(offset, bits)| *offset as usize + (1usize << bits))
+        .max()
+        .unwrap_or(0);
+    // Table is too big for the stack, so it is allocated on the heap
+    let mut table = vec![BitBoard::default(); table_size];
+    let mut magics_table = [Magic::default(); 64];
+
+    for square in Square::iter() {
+        let mask = match piece {
+            MagicPiece::ROOK => get_rook_relevant_occupancy_mask(square),
+            MagicPiece::BISHOP => get_bishop_relevant_occupancy_mask(square),
+        };
+        let bits = index_bits[square as usize];
+        let magic = Magic {
+            mask,
+            offset: offsets[square as usize] as u64,
+            shift: 64 - bits,
+            magic_number: magic_numbers[square as usize],
+        };
+
+        let blockers = generate_blockers(mask);
+        let attacks = match piece {
+            MagicPiece::ROOK => generate_rook_attacks(square, &blockers),
+            MagicPiece::BISHOP => generate_bishop_attacks(square, &blockers),
+        };
+
+        for (blocker, attack) in blockers.iter().zip(attacks.iter()) {
+            let entry = &mut table[magic.index(*blocker)];
+
+            // Slider always attacks at least one square, so empty entry is free
+            if entry.empty() {
+                *entry = *attack;
+            } else if entry != attack {
+                return None;
+            }
+        }
+
+        magics_table[square as usize] = magic;
+    }
+
+    while table.last().is_some_and(|entry| entry.empty()) {
+        table.pop();
+    }
+
+    Some((table, magics_table))
+}
+
 //////////////////////////////////////
 //    GENERATE BLOCKERS TABLES      //
 //////////////////////////////////////
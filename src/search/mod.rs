@@ -1,2 +1,5 @@
 pub mod negamax;
+pub mod ordering;
 pub mod quiescence;
+pub mod smp;
+pub mod transposition;
@@ -1,5 +1,8 @@
+use std::sync::atomic::Ordering;
+
 use crate::{
     board_repr::board::Board,
+    evaluation::eval::{active_params, EvalParams},
     move_generation::{
         generator::MoveGenerator,
         move_list::MoveList,
@@ -7,53 +10,208 @@ use crate::{
     },
 };
 
+use super::{
+    ordering::pick_move,
+    smp::{SearchResult, SharedState},
+    transposition::{Bound, TTEntry},
+};
+
+pub const INFINITY: i16 = 32000;
+pub const MATE_VALUE: i16 = 30000;
+// Scores above this value (by absolute value) mean that there is a forced mate
+pub const MATE_SCORE: i16 = 29000;
+pub const MAX_PLY: usize = 64;
+
+// How often (in nodes) search flushes node counter and checks if it has to stop
+const CHECK_INTERVAL: u64 = 1024;
+
 pub struct Search<'a> {
     pub board: &'a mut Board,
     pub mg: &'a MoveGenerator,
+    pub shared: &'a SharedState<'a>,
+    pub thread_id: usize,
 
-    pub nodes: u32,
+    pub nodes: u64,
     pub ply: u32,
     pub best_move: Option<Move>,
+    pub killer_moves: [[Move; 2]; MAX_PLY],
+    pub history_moves: [[i32; 64]; 12],
+    pub eval_params: EvalParams,
+
+    unflushed_nodes: u64,
 }
 
 impl<'a> Search<'a> {
-    pub fn new(board: &'a mut Board, mg: &'a MoveGenerator) -> Self {
+    pub fn new(
+        board: &'a mut Board,
+        mg: &'a MoveGenerator,
+        shared: &'a SharedState<'a>,
+        thread_id: usize,
+    ) -> Self {
         let nodes = 0;
         let ply = 0;
         let best_move = None;
         Self {
             board,
             mg,
+            shared,
+            thread_id,
 
             nodes,
             ply,
             best_move,
+            killer_moves: [[Move::default(); 2]; MAX_PLY],
+            history_moves: [[0; 64]; 12],
+            eval_params: active_params(),
+
+            unflushed_nodes: 0,
+        }
+    }
+
+    pub fn is_main_thread(&self) -> bool {
+        self.thread_id == 0
+    }
+}
+
+/*
+ * Iterative deepening.
+ * Searches position with depth 1, 2, 3... until depth or time limit is reached.
+ * Results of previous iterations are kept in the transposition table and used for move ordering.
+ *
+ * In Lazy SMP helper threads with odd id search one ply deeper than the main thread,
+ * so that threads do not walk the tree in exactly the same order.
+ */
+impl<'a> Search<'a> {
+    pub fn iterative_deepening(
+        &mut self,
+        mut on_iteration: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        let limits = &self.shared.limits;
+        let mut result = SearchResult::default();
+
+        for iteration in 1..=limits.depth {
+            let depth = (iteration + (self.thread_id % 2) as i8).min(limits.depth);
+            let score = self.alpha_beta(-INFINITY, INFINITY, depth);
+
+            if self.should_stop() {
+                break;
+            }
+
+            result.best_move = self.best_move;
+            result.score = score;
+            result.depth = depth;
+            result.nodes = self.shared.nodes.load(Ordering::Relaxed) + self.unflushed_nodes;
+            result.elapsed = self.shared.start.elapsed();
+
+            if self.is_main_thread() {
+                on_iteration(&result);
+
+                // There is no time to finish next iteration
+                if let Some(movetime) = limits.movetime {
+                    if result.elapsed * 2 > movetime {
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.flush_nodes();
+        result.nodes = self.nodes;
+
+        result
+    }
+
+    fn flush_nodes(&mut self) {
+        self.shared
+            .nodes
+            .fetch_add(self.unflushed_nodes, Ordering::Relaxed);
+        self.unflushed_nodes = 0;
+    }
+
+    pub fn count_node(&mut self) {
+        self.nodes += 1;
+        self.unflushed_nodes += 1;
+
+        if self.unflushed_nodes < CHECK_INTERVAL {
+            return;
         }
+
+        self.flush_nodes();
+
+        // Only the main thread decides when search is over
+        if self.is_main_thread() {
+            let limits = &self.shared.limits;
+            let out_of_time = limits
+                .movetime
+                .is_some_and(|movetime| self.shared.start.elapsed() >= movetime);
+            let out_of_nodes = limits
+                .nodes
+                .is_some_and(|nodes| self.shared.nodes.load(Ordering::Relaxed) >= nodes);
+
+            let aborted = self
+                .shared
+                .abort
+                .is_some_and(|abort| abort.load(Ordering::Relaxed));
+
+            if out_of_time || out_of_nodes || aborted {
+                self.shared.stop.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn should_stop(&self) -> bool {
+        self.shared.stop.load(Ordering::Relaxed)
     }
 }
 
 impl<'a> Search<'a> {
     pub fn alpha_beta(&mut self, mut alpha: i16, beta: i16, depth: i8) -> i16 {
+        if self.should_stop() {
+            return 0;
+        }
+
+        // Look up the position in transposition table. Root node always searched to get the best move.
+        let key = self.board.zobrist_key();
+        let tt_entry = self.shared.tt.probe(key);
+        if let Some(entry) = tt_entry {
+            if self.ply > 0 && entry.depth >= depth {
+                let score = score_from_tt(entry.score, self.ply);
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return beta,
+                    Bound::Upper if score <= alpha => return alpha,
+                    _ => (),
+                }
+            }
+        }
+
         // base condition
-        if depth == 0 {
-            return self.quiescence(alpha, beta);
+        if depth <= 0 {
+            return self.quiescence(alpha, beta, 0);
+        }
+
+        if self.ply as usize >= MAX_PLY - 1 {
+            return self.eval_params.evaluate(self.board);
         }
 
         // init variables
-        let mut best_move_so_far: Move = Move::default();
+        let mut best_move_so_far: Option<Move> = None;
         let mut legal_moves_count = 0;
         let init_alpha = alpha;
         let is_king_in_check = self.board.is_king_in_check(self.mg);
 
         // update number of nodes traversed
-        self.nodes += 1;
+        self.count_node();
 
         // make list of moves
         let mut move_list = MoveList::new();
         self.mg
             .generate_moves(self.board, &mut move_list, MoveType::All);
+        let mut scores = self.score_moves(&move_list, tt_entry.and_then(|e| e.best_move));
+
+        for index in 0..move_list.count as usize {
+            let move_data = pick_move(&mut move_list, &mut scores, index);
 
-        for move_data in move_list {
             let is_legal = self.board.make_move(move_data, self.mg);
             if !is_legal {
                 continue;
@@ -68,19 +226,31 @@ impl<'a> Search<'a> {
             self.ply -= 1;
             self.board.unmake_move();
 
+            // Search was interrupted, result of this node can not be trusted
+            if self.should_stop() {
+                return 0;
+            }
+
             // fail hard beta cutoff
             if score >= beta {
+                self.shared.tt.store(
+                    key,
+                    TTEntry {
+                        best_move: Some(move_data),
+                        score: score_to_tt(beta, self.ply),
+                        depth,
+                        bound: Bound::Lower,
+                    },
+                );
+                self.update_quiet_move_heuristics(move_data, depth);
+
                 return beta;
             }
 
             // found better move
             if score > alpha {
                 alpha = score;
-
-                // if root node
-                if self.ply == 0 {
-                    best_move_so_far = move_data;
-                }
+                best_move_so_far = Some(move_data);
             }
         }
 
@@ -89,18 +259,55 @@ impl<'a> Search<'a> {
             if is_king_in_check {
                 // We need to add ply here to ensure that mate with fewer number of moves
                 // going to have higher score.
-                return -30000i16 + self.ply as i16;
+                return -MATE_VALUE + self.ply as i16;
             }
 
             // Stalemate
             return 0;
         }
 
-        if init_alpha != alpha {
-            self.best_move = Some(best_move_so_far)
+        self.shared.tt.store(
+            key,
+            TTEntry {
+                best_move: best_move_so_far,
+                score: score_to_tt(alpha, self.ply),
+                depth,
+                bound: if alpha != init_alpha {
+                    Bound::Exact
+                } else {
+                    Bound::Upper
+                },
+            },
+        );
+
+        // if root node
+        if self.ply == 0 && best_move_so_far.is_some() {
+            self.best_move = best_move_so_far;
         }
 
         // fails low
         alpha
     }
 }
+
+// Mate scores are stored relative to the node, not to the root, so they stay valid
+// when the same position is reached at a different ply.
+fn score_to_tt(score: i16, ply: u32) -> i16 {
+    if score > MATE_SCORE {
+        score + ply as i16
+    } else if score < -MATE_SCORE {
+        score - ply as i16
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i16, ply: u32) -> i16 {
+    if score > MATE_SCORE {
+        score - ply as i16
+    } else if score < -MATE_SCORE {
+        score + ply as i16
+    } else {
+        score
+    }
+}
@@ -0,0 +1,109 @@
+/*
+ * Move ordering.
+ * Alpha-beta prunes the most when the best move is searched first, so moves are scored
+ * before the search and picked one by one starting from the highest score:
+ *   1. Best move from the transposition table
+ *   2. Captures, ordered by MVV-LVA (most valuable victim, least valuable attacker)
+ *   3. Killer moves (quiet moves that caused beta cutoff at the same ply)
+ *   4. Other quiet moves, ordered by history heuristic
+ */
+
+use crate::{
+    board_repr::piece::Piece,
+    move_generation::{
+        move_list::{MoveList, MAX_LEGAL_MOVES},
+        moves::Move,
+    },
+};
+
+use super::negamax::{Search, MAX_PLY};
+
+const TT_MOVE_SCORE: i32 = 1_000_000;
+const CAPTURE_SCORE: i32 = 100_000;
+const FIRST_KILLER_SCORE: i32 = 90_000;
+const SECOND_KILLER_SCORE: i32 = 80_000;
+
+pub type MoveScores = [i32; MAX_LEGAL_MOVES];
+
+pub fn is_capture(move_data: Move) -> bool {
+    !move_data.captured_piece().is_none() || move_data.en_passant()
+}
+
+pub fn mvv_lva(move_data: Move) -> i32 {
+    let victim = if move_data.en_passant() {
+        Piece::WhitePawn
+    } else {
+        move_data.captured_piece()
+    };
+    let attacker = move_data.piece();
+
+    victim.eval_value().abs() as i32 * 10 - attacker.eval_value().abs() as i32 / 100
+}
+
+impl<'a> Search<'a> {
+    pub fn score_move(&self, move_data: Move, tt_move: Option<Move>) -> i32 {
+        if tt_move == Some(move_data) {
+            return TT_MOVE_SCORE;
+        }
+
+        if is_capture(move_data) {
+            return CAPTURE_SCORE + mvv_lva(move_data);
+        }
+
+        let ply = (self.ply as usize).min(MAX_PLY - 1);
+        if self.killer_moves[ply][0] == move_data {
+            return FIRST_KILLER_SCORE;
+        }
+        if self.killer_moves[ply][1] == move_data {
+            return SECOND_KILLER_SCORE;
+        }
+
+        self.history_moves[move_data.piece() as usize][move_data.target_square() as usize]
+    }
+
+    pub fn score_moves(&self, move_list: &MoveList, tt_move: Option<Move>) -> MoveScores {
+        let mut scores = [0; MAX_LEGAL_MOVES];
+        for (index, move_data) in move_list
+            .moves
+            .iter()
+            .take(move_list.count as usize)
+            .enumerate()
+        {
+            scores[index] = self.score_move(*move_data, tt_move);
+        }
+
+        scores
+    }
+
+    // Quiet move that caused beta cutoff is likely to cause it in sibling nodes as well.
+    pub fn update_quiet_move_heuristics(&mut self, move_data: Move, depth: i8) {
+        if is_capture(move_data) {
+            return;
+        }
+
+        let ply = (self.ply as usize).min(MAX_PLY - 1);
+        if self.killer_moves[ply][0] != move_data {
+            self.killer_moves[ply][1] = self.killer_moves[ply][0];
+            self.killer_moves[ply][0] = move_data;
+        }
+
+        let history =
+            &mut self.history_moves[move_data.piece() as usize][move_data.target_square() as usize];
+        *history = (*history + depth as i32 * depth as i32).min(SECOND_KILLER_SCORE - 1);
+    }
+}
+
+// Finds the move with the highest score among not yet searched moves and swaps it to 'index'.
+pub fn pick_move(move_list: &mut MoveList, scores: &mut MoveScores, index: usize) -> Move {
+    let mut best_index = index;
+    for i in (index + 1)..move_list.count as usize {
+        if scores[i] > scores[best_index] {
+            best_index = i;
+        }
+    }
+
+    move_list.moves.swap(index, best_index);
+    scores.swap(index, best_index);
+
+    move_list.moves[index]
+}
@@ -1,44 +1,86 @@
-use crate::{
-    evaluation::eval::evaluate,
-    move_generation::{move_list::MoveList, moves::MoveType},
+use crate::move_generation::{move_list::MoveList, moves::MoveType};
+
+use super::{
+    negamax::{Search, MATE_VALUE, MAX_PLY},
+    ordering::pick_move,
 };
 
-use super::negamax::Search;
+// Search non-capturing checks at the first quiescence ply, catches mates right behind the horizon
+pub const QUIESCENCE_QUIET_CHECKS: bool = true;
 
 impl<'a> Search<'a> {
-    pub fn quiescence(&mut self, mut alpha: i16, beta: i16) -> i16 {
+    /*
+     * Depth starts at 0 and goes down with every quiescence ply.
+     * When in check there is no stand pat, all evasions are searched and having none is mate.
+     */
+    pub fn quiescence(&mut self, mut alpha: i16, beta: i16, depth: i8) -> i16 {
+        if self.should_stop() {
+            return 0;
+        }
+
+        // update number of nodes traversed
+        self.count_node();
+
         // evaluate position
-        let eval_score = evaluate(self.board);
+        let eval_score = self.eval_params.evaluate(self.board);
 
-        // fail hard beta cutoff
-        if eval_score >= beta {
-            return beta;
+        if self.ply as usize >= MAX_PLY - 1 {
+            return eval_score;
         }
 
-        // found better move
-        if eval_score > alpha {
-            alpha = eval_score;
+        let in_check = !self.mg.checkers(self.board).empty();
+
+        if !in_check {
+            // fail hard beta cutoff
+            if eval_score >= beta {
+                return beta;
+            }
+
+            // found better move
+            if eval_score > alpha {
+                alpha = eval_score;
+            }
         }
 
         // make list of moves
         let mut move_list = MoveList::new();
-        self.mg
-            .generate_moves(self.board, &mut move_list, MoveType::Capture);
+        if in_check {
+            self.mg
+                .generate_moves(self.board, &mut move_list, MoveType::Evasions);
+        } else {
+            self.mg
+                .generate_moves(self.board, &mut move_list, MoveType::Capture);
+            if QUIESCENCE_QUIET_CHECKS && depth >= 0 {
+                self.mg
+                    .generate_moves(self.board, &mut move_list, MoveType::QuietChecks);
+            }
+        }
+        let mut scores = self.score_moves(&move_list, None);
+        let mut legal_moves = 0;
 
-        for move_data in move_list {
-            let is_legal = self.board.make_move(move_data, self.mg);
-            if !is_legal {
+        for index in 0..move_list.count as usize {
+            let move_data = pick_move(&mut move_list, &mut scores, index);
+
+            // evasions are strictly legal, other moves have to be checked
+            if in_check {
+                self.board.make_legal_move(move_data);
+            } else if !self.board.make_move(move_data, self.mg) {
                 continue;
             }
+            legal_moves += 1;
 
             // increment counters
             self.ply += 1;
 
-            let score = -self.quiescence(-beta, -alpha);
+            let score = -self.quiescence(-beta, -alpha, depth - 1);
 
             self.ply -= 1;
             self.board.unmake_move();
 
+            if self.should_stop() {
+                return 0;
+            }
+
             // fail hard beta cutoff
             if score >= beta {
                 return beta;
@@ -50,7 +92,47 @@ impl<'a> Search<'a> {
             }
         }
 
+        // checkmate
+        if in_check && legal_moves == 0 {
+            return -MATE_VALUE + self.ply as i16;
+        }
+
         // fails low
         alpha
     }
 }
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board_repr::fen::Fen,
+        move_generation::generator::MoveGenerator,
+        search::{
+            negamax::{INFINITY, MATE_SCORE},
+            smp::{SearchLimits, SharedState},
+            transposition::TranspositionTable,
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_quiescence_sees_quiet_mate_and_evasions() {
+        let mg = MoveGenerator::shared();
+        let tt = TranspositionTable::new(1);
+        let shared = SharedState::new(&tt, SearchLimits::default());
+
+        // Ra8# is a quiet check found at the first quiescence ply
+        let mut board = Fen::to_board("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let mut search = Search::new(&mut board, mg, &shared, 0);
+        assert!(search.quiescence(-INFINITY, INFINITY, 0) > MATE_SCORE);
+
+        // Side to move is mated, there is no stand pat to fall back to
+        let mut board = Fen::to_board("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1");
+        let mut search = Search::new(&mut board, mg, &shared, 0);
+        assert!(search.quiescence(-INFINITY, INFINITY, 0) < -MATE_SCORE);
+    }
+}
@@ -0,0 +1,230 @@
+/*
+ * Lazy SMP.
+ * All threads search the same root position with their own copy of the board and their own
+ * killer/history tables. The only thing they share is the transposition table, so threads
+ * benefit from each other's results without any explicit synchronization.
+ * The main thread (id 0) owns the time control and stops helpers when it is done.
+ */
+
+use std::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    board_repr::{board::Board, fen::Fen},
+    move_generation::{generator::MoveGenerator, moves::Move},
+};
+
+use super::{
+    negamax::{Search, MAX_PLY},
+    transposition::TranspositionTable,
+};
+
+pub const MAX_THREADS: usize = 256;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SearchLimits {
+    pub depth: i8,
+    pub movetime: Option<Duration>,
+    pub nodes: Option<u64>,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        Self {
+            depth: (MAX_PLY - 1) as i8,
+            movetime: None,
+            nodes: None,
+        }
+    }
+}
+
+pub struct SharedState<'a> {
+    pub tt: &'a TranspositionTable,
+    pub limits: SearchLimits,
+    pub stop: AtomicBool,
+    pub nodes: AtomicU64,
+    pub start: Instant,
+    // Set from outside (GUI 'stop'), main thread notices it and stops the search
+    pub abort: Option<&'a AtomicBool>,
+}
+
+impl<'a> SharedState<'a> {
+    pub fn new(tt: &'a TranspositionTable, limits: SearchLimits) -> Self {
+        Self {
+            tt,
+            limits,
+            stop: AtomicBool::new(false),
+            nodes: AtomicU64::new(0),
+            start: Instant::now(),
+            abort: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: i16,
+    pub depth: i8,
+    pub nodes: u64,
+    pub elapsed: Duration,
+}
+
+impl SearchResult {
+    pub fn nps(&self) -> u64 {
+        let micros = self.elapsed.as_micros().max(1);
+        (self.nodes as u128 * 1_000_000 / micros) as u64
+    }
+}
+
+/*
+ * Runs the search on 'threads' threads and returns the best result.
+ * 'on_iteration' is called by the main thread after every completed iteration.
+ */
+pub fn lazy_smp(
+    board: &mut Board,
+    mg: &MoveGenerator,
+    tt: &TranspositionTable,
+    limits: SearchLimits,
+    threads: usize,
+    on_iteration: impl FnMut(&SearchResult),
+) -> SearchResult {
+    lazy_smp_with_abort(board, mg, tt, limits, threads, None, on_iteration)
+}
+
+// Same as 'lazy_smp', but search also ends as soon as 'abort' is set
+pub fn lazy_smp_with_abort(
+    board: &mut Board,
+    mg: &MoveGenerator,
+    tt: &TranspositionTable,
+    limits: SearchLimits,
+    threads: usize,
+    abort: Option<&AtomicBool>,
+    on_iteration: impl FnMut(&SearchResult),
+) -> SearchResult {
+    let mut shared = SharedState::new(tt, limits);
+    shared.abort = abort;
+    let threads = threads.clamp(1, MAX_THREADS);
+
+    let (main_result, helper_results) = thread::scope(|s| {
+        let helpers: Vec<_> = (1..threads)
+            .map(|thread_id| {
+                let mut helper_board = board.clone();
+                let shared = &shared;
+                s.spawn(move || {
+                    Search::new(&mut helper_board, mg, shared, thread_id)
+                        .iterative_deepening(|_| {})
+                })
+            })
+            .collect();
+
+        let main_result = Search::new(board, mg, &shared, 0).iterative_deepening(on_iteration);
+
+        // Main thread is done, helpers have to finish as well
+        shared.stop.store(true, Ordering::Relaxed);
+
+        let helper_results: Vec<SearchResult> = helpers
+            .into_iter()
+            .map(|helper| helper.join().expect("Search thread panicked"))
+            .collect();
+
+        (main_result, helper_results)
+    });
+
+    // Prefer the deepest completed iteration, main thread wins ties
+    let mut best = main_result;
+    for result in helper_results.iter() {
+        if result.best_move.is_some() && result.depth > best.depth {
+            best.best_move = result.best_move;
+            best.score = result.score;
+            best.depth = result.depth;
+        }
+    }
+
+    best.nodes = main_result.nodes + helper_results.iter().map(|r| r.nodes).sum::<u64>();
+    best.elapsed = shared.start.elapsed();
+
+    best
+}
+
+/*
+ * Fixed time benchmark that shows how search speed scales with number of threads.
+ * Every position is searched for 'movetime' with 1, 2, 4... up to 'max_threads' threads.
+ */
+pub fn scaling_benchmark(
+    mg: &MoveGenerator,
+    fens: &[&str],
+    movetime: Duration,
+    max_threads: usize,
+) {
+    let mut thread_counts = vec![];
+    let mut threads = 1;
+    while threads < max_threads {
+        thread_counts.push(threads);
+        threads *= 2;
+    }
+    thread_counts.push(max_threads.max(1));
+
+    let mut base_nps = 0u64;
+    for threads in thread_counts {
+        let mut total_nodes = 0u64;
+        let mut total_time = Duration::default();
+
+        for fen in fens {
+            let mut board = Fen::to_board(fen);
+            let tt = TranspositionTable::default();
+            let limits = SearchLimits {
+                movetime: Some(movetime),
+                ..Default::default()
+            };
+
+            let result = lazy_smp(&mut board, mg, &tt, limits, threads, |_| {});
+            total_nodes += result.nodes;
+            total_time += result.elapsed;
+        }
+
+        let nps = (total_nodes as u128 * 1_000_000 / total_time.as_micros().max(1)) as u64;
+        if base_nps == 0 {
+            base_nps = nps.max(1);
+        }
+
+        println!(
+            "Threads: {threads:>3}  Nodes: {total_nodes:>12}  NPS: {nps:>10}  Speedup: {:.2}",
+            nps as f64 / base_nps as f64
+        );
+    }
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board_repr::fen::Fen,
+        move_generation::generator::MoveGenerator,
+        search::{negamax::MATE_SCORE, transposition::TranspositionTable},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_lazy_smp_finds_mate() {
+        let mg = MoveGenerator::shared();
+        let tt = TranspositionTable::new(1);
+        // Back rank mate: Ra8#
+        let mut board = Fen::to_board("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let limits = SearchLimits {
+            depth: 3,
+            ..Default::default()
+        };
+
+        let result = lazy_smp(&mut board, mg, &tt, limits, 3, |_| {});
+
+        assert_eq!(result.best_move.unwrap().to_uci_string(), "a1a8");
+        assert!(result.score > MATE_SCORE);
+    }
+}
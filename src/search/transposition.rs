@@ -0,0 +1,185 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::move_generation::moves::Move;
+
+pub const DEFAULT_HASH_SIZE_MB: usize = 16;
+pub const MAX_HASH_SIZE_MB: usize = 4096;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Bound {
+    Exact,
+    // Score is at least this high (beta cutoff)
+    Lower,
+    // Score is at most this high (failed low)
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TTEntry {
+    pub best_move: Option<Move>,
+    pub score: i16,
+    pub depth: i8,
+    pub bound: Bound,
+}
+
+/*
+BINARY REPRESENTATION OF THE ENTRY DATA
+
+bits  0..32   best move (0 if there is no move)
+bits 32..48   score
+bits 48..56   depth
+bits 56..64   bound (0 - empty slot, 1 - exact, 2 - lower, 3 - upper)
+*/
+impl TTEntry {
+    fn pack(&self) -> u64 {
+        let move_data = self.best_move.map(|m| m.data()).unwrap_or(0) as u64;
+        let bound = match self.bound {
+            Bound::Exact => 1u64,
+            Bound::Lower => 2u64,
+            Bound::Upper => 3u64,
+        };
+
+        move_data | (self.score as u16 as u64) << 32 | (self.depth as u8 as u64) << 48 | bound << 56
+    }
+
+    fn unpack(data: u64) -> Option<Self> {
+        let bound = match data >> 56 {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            3 => Bound::Upper,
+            _ => return None,
+        };
+        let move_data = (data & 0xffffffff) as u32;
+
+        Some(Self {
+            best_move: (move_data != 0).then(|| Move::from_data(move_data)),
+            score: ((data >> 32) & 0xffff) as u16 as i16,
+            depth: ((data >> 48) & 0xff) as u8 as i8,
+            bound,
+        })
+    }
+}
+
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+/*
+ * Transposition table shared by all search threads.
+ * It is lock-free: every slot stores entry data and position key XOR-ed with that data.
+ * If two threads write into the same slot simultaneously, the key check on probe fails
+ * and torn entry is simply ignored.
+ */
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_HASH_SIZE_MB)
+    }
+}
+
+impl TranspositionTable {
+    pub fn new(size_mb: usize) -> Self {
+        let slot_count = (size_mb.max(1) * 1024 * 1024 / std::mem::size_of::<Slot>()).max(1);
+        let mut slots = Vec::with_capacity(slot_count);
+        slots.resize_with(slot_count, Slot::default);
+
+        Self { slots }
+    }
+
+    pub fn resize(&mut self, size_mb: usize) {
+        *self = Self::new(size_mb);
+    }
+
+    pub fn size_mb(&self) -> usize {
+        (self.slots.len() * std::mem::size_of::<Slot>() / (1024 * 1024)).max(1)
+    }
+
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn slot(&self, key: u64) -> &Slot {
+        &self.slots[(key % self.slots.len() as u64) as usize]
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TTEntry> {
+        let slot = self.slot(key);
+        let data = slot.data.load(Ordering::Relaxed);
+        let stored_key = slot.key.load(Ordering::Relaxed);
+
+        if stored_key ^ data != key {
+            return None;
+        }
+
+        TTEntry::unpack(data)
+    }
+
+    pub fn store(&self, key: u64, entry: TTEntry) {
+        let slot = self.slot(key);
+
+        // Keep deeper result of the same position
+        if let Some(existing) = self.probe(key) {
+            if existing.depth > entry.depth && entry.bound != Bound::Exact {
+                return;
+            }
+        }
+
+        let data = entry.pack();
+        slot.key.store(key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board_repr::{piece::Piece, square::Square},
+        move_generation::moves::Move,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_store_and_probe() {
+        let tt = TranspositionTable::new(1);
+        let move_data = Move::encode_move(
+            Square::E2,
+            Square::E4,
+            Piece::WhitePawn,
+            Piece::None,
+            Piece::None,
+            false,
+            false,
+            true,
+        );
+        let entry = TTEntry {
+            best_move: Some(move_data),
+            score: -1234,
+            depth: 7,
+            bound: Bound::Lower,
+        };
+
+        tt.store(0xdeadbeef, entry);
+        let probed = tt.probe(0xdeadbeef).unwrap();
+
+        assert!(probed.best_move == Some(move_data));
+        assert_eq!(probed.score, -1234);
+        assert_eq!(probed.depth, 7);
+        assert_eq!(probed.bound, Bound::Lower);
+        assert!(tt.probe(0xdeadbeef + 1).is_none());
+
+        tt.clear();
+        assert!(tt.probe(0xdeadbeef).is_none());
+    }
+}
@@ -0,0 +1,109 @@
+/*
+ * Bench.
+ * Searches a fixed set of positions to a fixed depth on a single thread with a fresh
+ * transposition table. Total node count is a functional signature of the search:
+ * refactors that should not change engine behaviour must not change it.
+ * NPS is printed to track speed regressions.
+ */
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    board_repr::fen::Fen,
+    move_generation::generator::MoveGenerator,
+    search::{
+        smp::{lazy_smp, SearchLimits},
+        transposition::TranspositionTable,
+    },
+    _CASTLING, _EN_PASSANT, _POSITION_3, _POSITION_4, _POSITION_5, _POSITION_6, _START_FEN,
+    _TRICKY_POSITION,
+};
+
+pub const DEFAULT_BENCH_DEPTH: i8 = 6;
+
+pub const BENCH_POSITIONS: [&str; 8] = [
+    _START_FEN,
+    _TRICKY_POSITION,
+    _POSITION_3,
+    _POSITION_4,
+    _POSITION_5,
+    _POSITION_6,
+    _EN_PASSANT,
+    _CASTLING,
+];
+
+// Hash size is fixed, so node count does not depend on engine options
+const BENCH_HASH_SIZE_MB: usize = 16;
+
+pub struct BenchResult {
+    pub nodes: u64,
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    pub fn nps(&self) -> u64 {
+        (self.nodes as u128 * 1_000_000 / self.elapsed.as_micros().max(1)) as u64
+    }
+}
+
+pub fn bench(mg: &MoveGenerator, depth: i8) -> BenchResult {
+    let tt = TranspositionTable::new(BENCH_HASH_SIZE_MB);
+    let limits = SearchLimits {
+        depth,
+        ..Default::default()
+    };
+
+    let mut nodes = 0u64;
+    let now = Instant::now();
+
+    for (i, fen) in BENCH_POSITIONS.iter().enumerate() {
+        let mut board = Fen::to_board(fen);
+        tt.clear();
+
+        let result = lazy_smp(&mut board, mg, &tt, limits, 1, |_| {});
+        nodes += result.nodes;
+
+        println!(
+            "Position {:>2}/{}: {} nodes, bestmove {}",
+            i + 1,
+            BENCH_POSITIONS.len(),
+            result.nodes,
+            result
+                .best_move
+                .map_or(String::from("(none)"), |m| m.to_uci_string())
+        );
+    }
+
+    let result = BenchResult {
+        nodes,
+        elapsed: now.elapsed(),
+    };
+
+    println!();
+    println!("Total time (ms) : {}", result.elapsed.as_millis());
+    println!("Nodes searched  : {}", result.nodes);
+    println!("Nodes/second    : {}", result.nps());
+
+    result
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use crate::move_generation::generator::MoveGenerator;
+
+    use super::bench;
+
+    #[test]
+    fn test_bench_is_deterministic() {
+        let mg = MoveGenerator::shared();
+
+        let first = bench(mg, 3);
+        let second = bench(mg, 3);
+
+        assert!(first.nodes > 0);
+        assert_eq!(first.nodes, second.nodes);
+    }
+}
@@ -0,0 +1,481 @@
+/*
+ * Self-play training data generator.
+ * Every game starts from a random opening: random legal plies played from the start position or
+ * from a random line of an opening file (FEN or EPD, one per line). The rest of the game is
+ * played by fixed node searches. Quiet positions (no check, best move is not a capture or
+ * promotion, score is not a mate) are recorded with the search score and the game result:
+ *
+ * chess_engine_rust datagen [--games N] [--nodes N] [--threads N] [--random-plies N] [--book FILE]
+ *                           [--seed N] [--out FILE] [--text FILE]
+ *
+ * Binary output is a stream of packed records with the score from the side to move.
+ * Text output has one "fen | score | result" line per position, score in centipawns and result
+ * (1.0, 0.5 or 0.0) both from white's point of view.
+ */
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+
+use crate::{
+    board_repr::{
+        board::Board,
+        epd::Epd,
+        fen::Fen,
+        packed::{GameResult, PackedBoard, PackedRecord, PackedWriter},
+        piece::{Color, Piece},
+    },
+    move_generation::{generator::MoveGenerator, move_list::MoveList},
+    search::{
+        negamax::MATE_SCORE,
+        smp::{lazy_smp, SearchLimits},
+        transposition::TranspositionTable,
+    },
+    _START_FEN,
+};
+
+// Game is adjudicated as a win once the score stays this high for a few moves in a row
+const WIN_ADJUDICATION_SCORE: i16 = 2000;
+const WIN_ADJUDICATION_PLIES: usize = 6;
+// Games still going after this many plies are adjudicated as draws
+const MAX_GAME_PLIES: usize = 400;
+const DATAGEN_HASH_SIZE_MB: usize = 16;
+// Random openings tried before giving up, every one may end the game within the random plies
+const MAX_OPENING_ATTEMPTS: usize = 1000;
+
+#[derive(Debug, Clone)]
+pub struct DatagenOptions {
+    pub games: u64,
+    pub nodes: u64,
+    pub threads: usize,
+    pub random_plies: usize,
+    // Opening positions, random plies are played on top of them
+    pub book: Vec<Board>,
+    pub seed: u64,
+}
+
+impl Default for DatagenOptions {
+    fn default() -> Self {
+        Self {
+            games: 100,
+            nodes: 5000,
+            threads: 1,
+            random_plies: 8,
+            book: vec![],
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    // Recorded positions with search scores from the side to move
+    pub positions: Vec<(Board, i16)>,
+    pub result: GameResult,
+}
+
+impl GameRecord {
+    pub fn packed_records(&self) -> Vec<PackedRecord> {
+        self.positions
+            .iter()
+            .map(|(board, score)| PackedRecord {
+                board: PackedBoard::from_board(board),
+                score: Some(*score),
+                result: Some(self.result),
+            })
+            .collect()
+    }
+
+    pub fn text_lines(&self) -> Vec<String> {
+        self.positions
+            .iter()
+            .map(|(board, score)| {
+                let white_score = match board.active_color() {
+                    Color::White => *score,
+                    _ => -*score,
+                };
+                format!(
+                    "{} | {} | {:.1}",
+                    Fen::from_board(board),
+                    white_score,
+                    self.result.white_score()
+                )
+            })
+            .collect()
+    }
+}
+
+// Neither side can mate: bare kings or a single minor piece
+pub fn is_insufficient_material(board: &Board) -> bool {
+    let count = |piece: Piece| {
+        board.bitboards[piece as usize].count_ones()
+            + board.bitboards[piece.opposite_color() as usize].count_ones()
+    };
+
+    count(Piece::WhitePawn) + count(Piece::WhiteRook) + count(Piece::WhiteQueen) == 0
+        && count(Piece::WhiteKnight) + count(Piece::WhiteBishop) <= 1
+}
+
+// Plays random legal moves, None if the game ends before all plies are played
+pub fn random_opening(
+    start: &Board,
+    plies: usize,
+    mg: &MoveGenerator,
+    rng: &mut impl Rng,
+) -> Option<Board> {
+    let mut board = start.clone();
+
+    for _ in 0..plies {
+        let mut move_list = MoveList::new();
+        mg.generate_legal_moves(&board, &mut move_list);
+        let move_data = move_list.moves[..move_list.count as usize].choose(rng)?;
+        board.make_legal_move(*move_data);
+    }
+
+    let mut move_list = MoveList::new();
+    mg.generate_legal_moves(&board, &mut move_list);
+    (move_list.count > 0).then_some(board)
+}
+
+// Random opening from the book (or the start position) that still has moves to play
+pub fn pick_opening(
+    options: &DatagenOptions,
+    mg: &MoveGenerator,
+    rng: &mut impl Rng,
+) -> Result<Board, String> {
+    let start_position = Fen::to_board(_START_FEN);
+
+    for _ in 0..MAX_OPENING_ATTEMPTS {
+        let start = options.book.choose(rng).unwrap_or(&start_position);
+        if let Some(board) = random_opening(start, options.random_plies, mg, rng) {
+            return Ok(board);
+        }
+    }
+
+    Err(format!(
+        "No playable opening in {MAX_OPENING_ATTEMPTS} attempts, every game ended within {} random plies",
+        options.random_plies
+    ))
+}
+
+pub fn play_game(
+    mut board: Board,
+    mg: &MoveGenerator,
+    tt: &TranspositionTable,
+    nodes: u64,
+) -> GameRecord {
+    let limits = SearchLimits {
+        nodes: Some(nodes),
+        ..Default::default()
+    };
+    let mut positions = vec![];
+    let mut keys = vec![board.zobrist_key()];
+    let mut winning_plies = 0;
+    let mut winning_side = 0;
+
+    let result = loop {
+        let mut move_list = MoveList::new();
+        mg.generate_legal_moves(&board, &mut move_list);
+        let in_check = !mg.checkers(&board).empty();
+        let repetitions = keys.iter().filter(|k| **k == board.zobrist_key()).count();
+
+        if move_list.count == 0 && in_check {
+            break match board.active_color() {
+                Color::White => GameResult::BlackWin,
+                _ => GameResult::WhiteWin,
+            };
+        }
+        if move_list.count == 0
+            || repetitions >= 3
+            || board.game_state.halfmove_clock >= 100
+            || is_insufficient_material(&board)
+            || keys.len() > MAX_GAME_PLIES
+        {
+            break GameResult::Draw;
+        }
+
+        let mut search_board = board.clone();
+        let search = lazy_smp(&mut search_board, mg, tt, limits, 1, |_| {});
+        let move_data = search.best_move.unwrap_or(move_list.moves[0]);
+        let score = search.score;
+
+        // Plies in a row one side has been winning by far
+        let white_score = match board.active_color() {
+            Color::White => score,
+            _ => -score,
+        };
+        winning_plies = match white_score.abs() >= WIN_ADJUDICATION_SCORE {
+            true if white_score.signum() == winning_side => winning_plies + 1,
+            true => 1,
+            false => 0,
+        };
+        winning_side = white_score.signum();
+        if winning_plies >= WIN_ADJUDICATION_PLIES {
+            break match winning_side {
+                1 => GameResult::WhiteWin,
+                _ => GameResult::BlackWin,
+            };
+        }
+
+        let is_quiet = move_data.captured_piece().is_none()
+            && !move_data.en_passant()
+            && move_data.promoted_piece().is_none();
+        if search.best_move.is_some() && !in_check && is_quiet && score.abs() < MATE_SCORE {
+            positions.push((board.clone(), score));
+        }
+
+        board.make_legal_move(move_data);
+        keys.push(board.zobrist_key());
+    };
+
+    GameRecord { positions, result }
+}
+
+// Output files shared by generator threads
+struct DatagenOutput {
+    binary: Option<PackedWriter<BufWriter<File>>>,
+    text: Option<BufWriter<File>>,
+}
+
+fn write_game(output: &Mutex<DatagenOutput>, game: &GameRecord) -> std::io::Result<()> {
+    let mut output = output.lock().unwrap();
+
+    if let Some(writer) = output.binary.as_mut() {
+        for record in game.packed_records() {
+            writer.write(&record)?;
+        }
+    }
+    if let Some(writer) = output.text.as_mut() {
+        for line in game.text_lines() {
+            writeln!(writer, "{line}")?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn generate_data(options: &DatagenOptions, output_files: (Option<&str>, Option<&str>)) {
+    let mg = MoveGenerator::shared();
+    let output = match (
+        output_files.0.map(PackedWriter::create).transpose(),
+        output_files
+            .1
+            .map(|path| File::create(path).map(BufWriter::new))
+            .transpose(),
+    ) {
+        (Ok(binary), Ok(text)) => Mutex::new(DatagenOutput { binary, text }),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Can not create output file: {e}");
+            return;
+        }
+    };
+
+    let games_played = AtomicU64::new(0);
+    let positions = AtomicU64::new(0);
+    let start = Instant::now();
+    let threads = options.threads.max(1);
+
+    thread::scope(|s| {
+        for thread_id in 0..threads {
+            let (output, games_played, positions) = (&output, &games_played, &positions);
+            s.spawn(move || {
+                let mut rng = ChaChaRng::seed_from_u64(options.seed.wrapping_add(thread_id as u64));
+                let tt = TranspositionTable::new(DATAGEN_HASH_SIZE_MB);
+
+                while games_played.fetch_add(1, Ordering::Relaxed) < options.games {
+                    let opening = match pick_opening(options, mg, &mut rng) {
+                        Ok(opening) => opening,
+                        Err(e) => {
+                            eprintln!("Thread {thread_id}: {e}");
+                            return;
+                        }
+                    };
+
+                    tt.clear();
+                    let game = play_game(opening, mg, &tt, options.nodes);
+                    if let Err(e) = write_game(output, &game) {
+                        eprintln!("Can not write generated data: {e}");
+                        return;
+                    }
+
+                    let total = positions.fetch_add(game.positions.len() as u64, Ordering::Relaxed)
+                        + game.positions.len() as u64;
+                    let elapsed = start.elapsed().as_secs_f64().max(0.001);
+                    eprintln!(
+                        "Thread {thread_id}: game {:?}, {} positions, {total} total ({:.0} pos/s)",
+                        game.result,
+                        game.positions.len(),
+                        total as f64 / elapsed
+                    );
+                }
+            });
+        }
+    });
+
+    let mut output = output.into_inner().unwrap();
+    let flushed = output
+        .binary
+        .as_mut()
+        .map_or(Ok(()), |w| w.flush())
+        .and(output.text.as_mut().map_or(Ok(()), |w| w.flush()));
+    if let Err(e) = flushed {
+        eprintln!("Can not write generated data: {e}");
+    }
+    eprintln!(
+        "Generated {} positions in {:.1} s",
+        positions.load(Ordering::Relaxed),
+        start.elapsed().as_secs_f64()
+    );
+}
+
+// FEN or EPD lines, EPD operations are parsed and ignored
+pub fn parse_book(text: &str) -> Result<Vec<Board>, String> {
+    let mg = MoveGenerator::shared();
+    let mut book = vec![];
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let epd = Epd::parse(line, mg).map_err(|e| format!("line {}: {e}", i + 1))?;
+        book.push(epd.board);
+    }
+
+    Ok(book)
+}
+
+fn parse_options(
+    args: &[String],
+) -> Result<(DatagenOptions, Option<String>, Option<String>), String> {
+    let mut options = DatagenOptions::default();
+    let mut binary = None;
+    let mut text = None;
+    let mut args = args.iter();
+
+    while let Some(name) = args.next() {
+        let value = args.next().ok_or(format!("Missing value for {name}"))?;
+        let number = || {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("Invalid value for {name}: {value}"))
+        };
+
+        match name.as_str() {
+            "--games" => options.games = number()?,
+            "--nodes" => options.nodes = number()?,
+            "--threads" => options.threads = number()? as usize,
+            "--random-plies" => options.random_plies = number()? as usize,
+            "--seed" => options.seed = number()?,
+            "--book" => {
+                let book = fs::read_to_string(value)
+                    .map_err(|e| format!("Can not read opening book {value}: {e}"))?;
+                options.book = parse_book(&book).map_err(|e| format!("{value}: {e}"))?;
+            }
+            "--out" => binary = Some(value.clone()),
+            "--text" => text = Some(value.clone()),
+            _ => return Err(format!("Unknown argument {name}")),
+        }
+    }
+
+    if binary.is_none() && text.is_none() {
+        binary = Some(String::from("data.bin"));
+    }
+
+    Ok((options, binary, text))
+}
+
+// Command line entry: "chess_engine_rust datagen [--games N] [--nodes N] [--threads N] ..."
+pub fn run_datagen(args: &[String]) {
+    match parse_options(args) {
+        Ok((options, binary, text)) => {
+            generate_data(&options, (binary.as_deref(), text.as_deref()))
+        }
+        Err(e) => eprintln!("{e}"),
+    }
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_game_end_detection() {
+        let mg = MoveGenerator::shared();
+        let tt = TranspositionTable::new(1);
+
+        // Mate in one is found and the mated side loses
+        let board = Fen::to_board("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let game = play_game(board, mg, &tt, 2000);
+        assert_eq!(game.result, GameResult::WhiteWin);
+
+        let board = Fen::to_board("8/8/4k3/8/8/3NK3/8/8 w - - 0 1");
+        assert!(is_insufficient_material(&board));
+        assert_eq!(play_game(board, mg, &tt, 2000).result, GameResult::Draw);
+        assert!(!is_insufficient_material(&Fen::to_board(_START_FEN)));
+    }
+
+    #[test]
+    fn test_opening_book_and_attempts() {
+        let mg = MoveGenerator::shared();
+        let mut rng = ChaChaRng::seed_from_u64(1);
+
+        let book = parse_book(
+            "# openings\nrnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - bm Nf3; id \"x\";\n\
+             rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1\n",
+        )
+        .unwrap();
+        assert_eq!(book.len(), 2);
+        assert!(parse_book("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - bm e9;").is_err());
+
+        // Mated position has no playable opening
+        let options = DatagenOptions {
+            book: vec![Fen::to_board("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1")],
+            random_plies: 0,
+            ..Default::default()
+        };
+        assert!(pick_opening(&options, mg, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_random_game_records_quiet_positions() {
+        let mg = MoveGenerator::shared();
+        let tt = TranspositionTable::new(1);
+        let mut rng = ChaChaRng::seed_from_u64(7);
+
+        let opening = random_opening(&Fen::to_board(_START_FEN), 8, mg, &mut rng).unwrap();
+        assert_eq!(opening.game_state.fullmove_number, 5);
+
+        // End of a rook endgame keeps the test short
+        let board = Fen::to_board("8/5k2/8/3r4/8/8/2R1K3/8 w - - 0 1");
+        let game = play_game(board, mg, &tt, 1500);
+        assert!(!game.positions.is_empty());
+        for (board, _) in &game.positions {
+            assert!(mg.checkers(board).empty());
+        }
+
+        let lines = game.text_lines();
+        let records = game.packed_records();
+        assert_eq!(lines.len(), records.len());
+        let (fen, rest) = lines[0].split_once(" | ").unwrap();
+        assert_eq!(fen, Fen::from_board(&game.positions[0].0));
+        assert_eq!(rest.split(" | ").count(), 2);
+        assert_eq!(
+            records[0].board.to_board().unwrap().zobrist_key(),
+            game.positions[0].0.zobrist_key()
+        );
+    }
+}
@@ -0,0 +1,519 @@
+/*
+ * Magic number finder.
+ * Searches magic numbers for rook and bishop attack lookups with a fixed seed, so the same
+ * arguments always produce the same numbers, verifies them against the reference attack generators
+ * and writes Rust source that replaces 'move_generation/magic_numbers.rs':
+ *
+ * chess_engine_rust magics [--seed N] [--fixed-shift] [--attempts N] [--out FILE]
+ *
+ * By default every square uses an index as long as its relevant occupancy mask and square tables
+ * follow each other. With '--fixed-shift' all squares share one index length and their tables
+ * overlap in a single array (see 'find_fixed_shift_magics'). How tight the array gets depends on
+ * '--attempts', the printed table sizes tell which layout is worth keeping.
+ * Table layout, per-square offsets and sizes are written with the magics.
+ */
+
+use std::fs;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+use strum::IntoEnumIterator;
+
+use crate::{
+    board_repr::{bit_board::BitBoard, square::Square},
+    move_generation::{
+        magics::MagicPiece,
+        tables::{
+            generate_bishop_attacks, generate_blockers, generate_rook_attacks,
+            generate_slider_attack_table, get_bishop_relevant_occupancy_mask,
+            get_rook_relevant_occupancy_mask,
+        },
+    },
+};
+
+pub const DEFAULT_MAGIC_SEED: u64 = 0x5eed;
+// Magics tried per square by the fixed-shift search, more attempts pack the tables tighter
+pub const DEFAULT_FIXED_SHIFT_ATTEMPTS: u64 = 8;
+
+#[derive(Debug, Clone, Copy)]
+pub struct MagicSearchOptions {
+    pub seed: u64,
+    pub fixed_shift: bool,
+    pub attempts: u64,
+}
+
+impl Default for MagicSearchOptions {
+    fn default() -> Self {
+        Self {
+            seed: DEFAULT_MAGIC_SEED,
+            fixed_shift: false,
+            attempts: DEFAULT_FIXED_SHIFT_ATTEMPTS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundMagics {
+    pub piece: MagicPiece,
+    pub magic_numbers: [u64; 64],
+    pub index_bits: [u8; 64],
+    // Position of the first entry of every square in the shared attack table
+    pub offsets: [u32; 64],
+    pub table_size: usize,
+}
+
+fn relevant_occupancy_mask(piece: MagicPiece, square: Square) -> BitBoard {
+    match piece {
+        MagicPiece::ROOK => get_rook_relevant_occupancy_mask(square),
+        MagicPiece::BISHOP => get_bishop_relevant_occupancy_mask(square),
+    }
+}
+
+fn slider_attacks(piece: MagicPiece, square: Square, blockers: &[BitBoard]) -> Vec<BitBoard> {
+    match piece {
+        MagicPiece::ROOK => generate_rook_attacks(square, blockers),
+        MagicPiece::BISHOP => generate_bishop_attacks(square, blockers),
+    }
+}
+
+// Tests random candidates, returns the first one without destructive collisions
+fn find_square_magic(
+    rng: &mut ChaChaRng,
+    mask: BitBoard,
+    blockers: &[BitBoard],
+    attacks: &[BitBoard],
+    index_bits: u8,
+) -> u64 {
+    // Entries are tagged with the attempt that wrote them, so the table never has to be cleared
+    let mut table = vec![(0u64, BitBoard::default()); 1 << index_bits];
+    let shift = 64 - index_bits;
+    let mut attempt = 0u64;
+
+    loop {
+        // Sparse candidates work much better, and good magics spread mask bits into the top byte
+        let candidate = rng.gen::<u64>() & rng.gen::<u64>() & rng.gen::<u64>();
+        if (mask.value().wrapping_mul(candidate) >> 56).count_ones() < 6 {
+            continue;
+        }
+        attempt += 1;
+
+        let mut found = true;
+        for (blocker, attack) in blockers.iter().zip(attacks.iter()) {
+            let index = ((blocker.value().wrapping_mul(candidate)) >> shift) as usize;
+            let entry = &mut table[index];
+
+            if entry.0 != attempt {
+                *entry = (attempt, *attack);
+            } else if entry.1 != *attack {
+                found = false;
+                break;
+            }
+        }
+
+        if found {
+            return candidate;
+        }
+    }
+}
+
+// Used entries of one square's table as (index, attack), constructive collisions are merged
+fn square_entries(
+    magic: u64,
+    index_bits: u8,
+    blockers: &[BitBoard],
+    attacks: &[BitBoard],
+) -> Vec<(usize, BitBoard)> {
+    let mut entries: Vec<(usize, BitBoard)> = blockers
+        .iter()
+        .zip(attacks.iter())
+        .map(|(blocker, attack)| {
+            let index = blocker.value().wrapping_mul(magic) >> (64 - index_bits);
+            (index as usize, *attack)
+        })
+        .collect();
+    entries.sort_by_key(|(index, _)| *index);
+    entries.dedup_by_key(|(index, _)| *index);
+    entries
+}
+
+// Lowest offset at which every entry lands on a free slot or on a slot holding the same attack
+fn lowest_fitting_offset(table: &[BitBoard], entries: &[(usize, BitBoard)]) -> usize {
+    (0..=table.len())
+        .find(|offset| {
+            entries
+                .iter()
+                .all(|(index, attack)| match table.get(offset + index) {
+                    // Slider always attacks at least one square, so empty entry is free
+                    Some(entry) => entry.empty() || entry == attack,
+                    None => true,
+                })
+        })
+        .unwrap_or(table.len())
+}
+
+pub fn find_magics(piece: MagicPiece, options: &MagicSearchOptions) -> FoundMagics {
+    match options.fixed_shift {
+        true => find_fixed_shift_magics(piece, options),
+        false => find_plain_magics(piece, options),
+    }
+}
+
+// Index as long as the relevant occupancy mask, square tables follow each other
+fn find_plain_magics(piece: MagicPiece, options: &MagicSearchOptions) -> FoundMagics {
+    let mut rng = ChaChaRng::seed_from_u64(options.seed);
+    let mut magic_numbers = [0u64; 64];
+    let mut index_bits = [0u8; 64];
+    let mut offsets = [0u32; 64];
+    let mut table_size = 0;
+
+    for square in Square::iter() {
+        let mask = relevant_occupancy_mask(piece, square);
+        let blockers = generate_blockers(mask);
+        let attacks = slider_attacks(piece, square, &blockers);
+        let bits = mask.count_ones() as u8;
+
+        magic_numbers[square as usize] =
+            find_square_magic(&mut rng, mask, &blockers, &attacks, bits);
+        index_bits[square as usize] = bits;
+        offsets[square as usize] = table_size as u32;
+        table_size += 1 << bits;
+    }
+
+    FoundMagics {
+        piece,
+        magic_numbers,
+        index_bits,
+        offsets,
+        table_size,
+    }
+}
+
+/*
+ * Every square gets the index length of the longest mask, so a square with a short mask uses
+ * only a part of its index range. Square tables are then packed into one array where they
+ * overlap: an entry may be shared if it is unused by one of the squares or both store the same
+ * attack. Squares with the most entries are placed first, each one tries 'attempts' magics and
+ * keeps the one that grows the array the least.
+ */
+fn find_fixed_shift_magics(piece: MagicPiece, options: &MagicSearchOptions) -> FoundMagics {
+    let mut rng = ChaChaRng::seed_from_u64(options.seed);
+    let mut magic_numbers = [0u64; 64];
+    let mut offsets = [0u32; 64];
+    let mut table: Vec<BitBoard> = vec![];
+
+    let masks: Vec<BitBoard> = Square::iter()
+        .map(|square| relevant_occupancy_mask(piece, square))
+        .collect();
+    let bits = masks
+        .iter()
+        .map(|mask| mask.count_ones())
+        .max()
+        .unwrap_or(0) as u8;
+    let mut squares: Vec<Square> = Square::iter().collect();
+    squares.sort_by_key(|square| std::cmp::Reverse(masks[*square as usize].count_ones()));
+
+    for square in squares {
+        let mask = masks[square as usize];
+        let blockers = generate_blockers(mask);
+        let attacks = slider_attacks(piece, square, &blockers);
+
+        // (table end after placing, offset, magic)
+        let mut best: Option<(usize, usize, u64)> = None;
+        for _ in 0..options.attempts.max(1) {
+            let magic = find_square_magic(&mut rng, mask, &blockers, &attacks, bits);
+            let entries = square_entries(magic, bits, &blockers, &attacks);
+            let offset = lowest_fitting_offset(&table, &entries);
+            let last_index = entries.last().map_or(0, |(index, _)| *index);
+            let end = table.len().max(offset + last_index + 1);
+
+            if best.as_ref().is_none_or(|b| (end, offset) < (b.0, b.1)) {
+                best = Some((end, offset, magic));
+            }
+        }
+
+        let (end, offset, magic) = best.expect("At least one magic is tried");
+        let entries = square_entries(magic, bits, &blockers, &attacks);
+        table.resize(end, BitBoard::default());
+        for (index, attack) in entries {
+            table[offset + index] = attack;
+        }
+        magic_numbers[square as usize] = magic;
+        offsets[square as usize] = offset as u32;
+    }
+
+    FoundMagics {
+        piece,
+        magic_numbers,
+        index_bits: [bits; 64],
+        offsets,
+        table_size: table.len(),
+    }
+}
+
+/*
+ * Builds the table exactly as the engine does and checks the lookup of every blocker subset
+ * of every square against the reference attack generators.
+ */
+pub fn verify_magics(magics: &FoundMagics) -> Result<(), String> {
+    let (table, magics_table) = generate_slider_attack_table(
+        magics.piece,
+        &magics.magic_numbers,
+        &magics.index_bits,
+        &magics.offsets,
+    )
+    .ok_or(format!(
+        "{} magics have destructive collisions",
+        magics.piece
+    ))?;
+    if table.len() != magics.table_size {
+        return Err(format!(
+            "{} table takes {} entries, {} expected",
+            magics.piece,
+            table.len(),
+            magics.table_size
+        ));
+    }
+
+    for square in Square::iter() {
+        let mask = relevant_occupancy_mask(magics.piece, square);
+        let blockers = generate_blockers(mask);
+        let attacks = slider_attacks(magics.piece, square, &blockers);
+
+        for (blocker, attack) in blockers.iter().zip(attacks.iter()) {
+            if table[magics_table[square as usize].index(*blocker)] != *attack {
+                return Err(format!(
+                    "{} magic for {square} gives wrong attack",
+                    magics.piece
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn format_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut result = String::new();
+
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            result.push('_');
+        }
+        result.push(c);
+    }
+
+    result
+}
+
+fn write_magics(source: &mut String, magics: &FoundMagics) {
+    let name = magics.piece.to_string();
+
+    source.push_str(&format!("pub const {name}_MAGICS: [u64; 64] = [\n"));
+    for magic in magics.magic_numbers {
+        source.push_str(&format!("    {magic}u64,\n"));
+    }
+    source.push_str("];\n\n");
+
+    source.push_str(&format!("pub const {name}_INDEX_BITS: [u8; 64] = [\n"));
+    for rank in magics.index_bits.chunks(8) {
+        let bits: Vec<String> = rank.iter().map(|b| b.to_string()).collect();
+        source.push_str(&format!("    {},\n", bits.join(", ")));
+    }
+    source.push_str("];\n\n");
+
+    source.push_str(&format!("pub const {name}_OFFSETS: [u32; 64] = [\n"));
+    for rank in magics.offsets.chunks(8) {
+        let offsets: Vec<String> = rank.iter().map(|o| o.to_string()).collect();
+        source.push_str(&format!("    {},\n", offsets.join(", ")));
+    }
+    source.push_str("];\n");
+}
+
+pub fn magics_source(
+    rook: &FoundMagics,
+    bishop: &FoundMagics,
+    options: &MagicSearchOptions,
+) -> String {
+    let mut source = String::new();
+
+    source.push_str("/*\n");
+    source.push_str(" * Magic numbers for slider attack lookups.\n");
+    let mut command = format!("chess_engine_rust magics --seed {}", options.seed);
+    if options.fixed_shift {
+        command.push_str(&format!(" --fixed-shift --attempts {}", options.attempts));
+    }
+    source.push_str(&format!(
+        " * Generated by '{command}', do not edit by hand.\n"
+    ));
+    source.push_str(" *\n");
+    source.push_str(" * Index of the attack is ((blockers & mask) * magic) >> (64 - index bits) + square offset.\n");
+    source.push_str(
+        " * Tables of different squares may overlap where they store the same attack or leave gaps.\n",
+    );
+    source.push_str(" */\n\n");
+
+    source.push_str(&format!(
+        "pub const ROOK_TABLE_SIZE: usize = {};\n",
+        format_thousands(rook.table_size)
+    ));
+    source.push_str(&format!(
+        "pub const BISHOP_TABLE_SIZE: usize = {};\n\n",
+        format_thousands(bishop.table_size)
+    ));
+
+    write_magics(&mut source, rook);
+    source.push('\n');
+    write_magics(&mut source, bishop);
+
+    source
+}
+
+fn parse_options(args: &[String]) -> Result<(MagicSearchOptions, Option<String>), String> {
+    let mut options = MagicSearchOptions::default();
+    let mut output = None;
+    let mut args = args.iter();
+
+    while let Some(name) = args.next() {
+        if name == "--fixed-shift" {
+            options.fixed_shift = true;
+            continue;
+        }
+
+        let value = args.next().ok_or(format!("Missing value for {name}"))?;
+        let number = || {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("Invalid value for {name}: {value}"))
+        };
+
+        match name.as_str() {
+            "--seed" => options.seed = number()?,
+            "--attempts" => options.attempts = number()?,
+            "--out" => output = Some(value.clone()),
+            _ => return Err(format!("Unknown argument {name}")),
+        }
+    }
+
+    Ok((options, output))
+}
+
+// Command line entry: "chess_engine_rust magics [--seed N] [--fixed-shift] [--attempts N] [--out FILE]"
+pub fn run_magic_finder(args: &[String]) {
+    let (options, output) = match parse_options(args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+
+    let rook = find_magics(MagicPiece::ROOK, &options);
+    let bishop = find_magics(MagicPiece::BISHOP, &options);
+
+    for magics in [&rook, &bishop] {
+        if let Err(e) = verify_magics(magics) {
+            eprintln!("Verification failed: {e}");
+            return;
+        }
+        eprintln!(
+            "{}: {} entries ({} KiB), verified",
+            magics.piece,
+            magics.table_size,
+            magics.table_size * std::mem::size_of::<BitBoard>() / 1024
+        );
+    }
+
+    let source = magics_source(&rook, &bishop, &options);
+    match output {
+        Some(path) => match fs::write(&path, source) {
+            Ok(()) => eprintln!("Written to {path}"),
+            Err(e) => eprintln!("Can not write {path}: {e}"),
+        },
+        None => print!("{source}"),
+    }
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use crate::move_generation::magics::{
+        BISHOP_INDEX_BITS, BISHOP_MAGICS, BISHOP_OFFSETS, BISHOP_TABLE_SIZE, ROOK_INDEX_BITS,
+        ROOK_MAGICS, ROOK_OFFSETS, ROOK_TABLE_SIZE,
+    };
+
+    use super::*;
+
+    fn engine_rook_magics() -> FoundMagics {
+        FoundMagics {
+            piece: MagicPiece::ROOK,
+            magic_numbers: ROOK_MAGICS,
+            index_bits: ROOK_INDEX_BITS,
+            offsets: ROOK_OFFSETS,
+            table_size: ROOK_TABLE_SIZE,
+        }
+    }
+
+    #[test]
+    fn test_engine_magics_are_valid() {
+        let bishop = FoundMagics {
+            piece: MagicPiece::BISHOP,
+            magic_numbers: BISHOP_MAGICS,
+            index_bits: BISHOP_INDEX_BITS,
+            offsets: BISHOP_OFFSETS,
+            table_size: BISHOP_TABLE_SIZE,
+        };
+
+        assert_eq!(verify_magics(&engine_rook_magics()), Ok(()));
+        assert_eq!(verify_magics(&bishop), Ok(()));
+    }
+
+    #[test]
+    fn test_find_magics_is_deterministic() {
+        let options = MagicSearchOptions {
+            seed: 7,
+            ..Default::default()
+        };
+
+        let first = find_magics(MagicPiece::BISHOP, &options);
+        let second = find_magics(MagicPiece::BISHOP, &options);
+
+        assert_eq!(first, second);
+        assert_eq!(verify_magics(&first), Ok(()));
+        assert_eq!(first.table_size, BISHOP_TABLE_SIZE);
+    }
+
+    #[test]
+    fn test_fixed_shift_tables_overlap() {
+        let options = MagicSearchOptions {
+            seed: 7,
+            fixed_shift: true,
+            attempts: 2,
+        };
+
+        let first = find_magics(MagicPiece::BISHOP, &options);
+        let second = find_magics(MagicPiece::BISHOP, &options);
+
+        assert_eq!(first, second);
+        assert_eq!(verify_magics(&first), Ok(()));
+        assert!(first.index_bits.iter().all(|bits| *bits == 9));
+        // 64 squares of 9 bits would take 32_768 entries laid out one after another
+        assert!(first.table_size < 64 << 9, "{}", first.table_size);
+    }
+
+    #[test]
+    fn test_magic_numbers_file_is_reproducible() {
+        let options = MagicSearchOptions::default();
+        let bishop = find_magics(MagicPiece::BISHOP, &options);
+        let rook = engine_rook_magics();
+
+        // Rook search is too slow for a debug test, bishop one proves the seed is the same
+        assert_eq!(bishop.magic_numbers, BISHOP_MAGICS);
+        assert_eq!(
+            magics_source(&rook, &bishop, &options),
+            include_str!("../move_generation/magic_numbers.rs")
+        );
+    }
+}
@@ -1,2 +1,8 @@
+pub mod bench;
+pub mod datagen;
+pub mod magic_finder;
 pub mod perft;
+pub mod perft_suite;
+pub mod pgn;
+pub mod test_suite;
 pub mod traits;
@@ -1,48 +1,132 @@
-use std::time::Instant;
+use std::{
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    thread,
+};
 
 use crate::{
     board_repr::board::Board,
-    move_generation::{generator::MoveGenerator, move_list::MoveList, moves::MoveType},
+    move_generation::{
+        generator::MoveGenerator,
+        move_list::MoveList,
+        moves::{Move, MoveType},
+    },
 };
 
-pub fn perft_test(board: &mut Board, mg: &MoveGenerator, depth: i8) {
-    let mut total_time = 0u128;
-    let mut total_nodes = 0u64;
+/*
+ * Prints divide output in the de-facto standard format, so it can be compared
+ * with reference engines by tools like perftree:
+ *
+ * e2e4: 20
+ * ...
+ *
+ * Nodes searched: 400
+ */
+pub fn perft_test(
+    board: &Board,
+    mg: &MoveGenerator,
+    depth: i8,
+    threads: usize,
+    table: &PerftTable,
+) {
+    let divide = perft_divide_parallel(board, mg, depth, Some(table), threads);
 
-    let mut move_list = MoveList::new();
-    mg.generate_moves(board, &mut move_list, MoveType::All);
+    for (move_data, nodes) in divide.iter() {
+        println!("{}: {nodes}", move_data.to_uci_string());
+    }
 
-    for i in 0..move_list.count {
-        let now = Instant::now();
-        let mut leaf_nodes = 0;
-        let move_data = move_list.moves[i as usize];
+    println!();
+    println!(
+        "Nodes searched: {}",
+        divide.iter().map(|(_, n)| n).sum::<u64>()
+    );
+}
 
-        if board.make_move(move_data, mg) {
-            leaf_nodes += perft(board, mg, depth - 1);
+// Returns number of leaf nodes for every legal root move, in move generation order.
+pub fn perft_divide(
+    board: &mut Board,
+    mg: &MoveGenerator,
+    depth: i8,
+    table: Option<&PerftTable>,
+) -> Vec<(Move, u64)> {
+    let mut result = vec![];
+    if depth <= 0 {
+        return result;
+    }
 
-            // Measure time and speed
-            let elapsed = now.elapsed().as_millis();
+    let mut move_list = MoveList::new();
+    mg.generate_legal_moves(board, &mut move_list);
 
-            // Add tot totals for final calculation at the very end.
-            total_time += elapsed;
-            total_nodes += leaf_nodes;
+    for move_data in move_list {
+        board.make_legal_move(move_data);
+        let leaf_nodes = perft_bulk(board, mg, depth - 1, table);
+        board.unmake_move();
 
-            board.unmake_move();
+        result.push((move_data, leaf_nodes));
+    }
 
-            // Print the results.
-            println!(
-                "{i}: {}{}: {leaf_nodes} ({elapsed} ms)",
-                move_data.source_square(),
-                move_data.target_square()
-            );
-        }
+    result
+}
+
+/*
+ * Same as 'perft_divide', but root moves are split between 'threads' scoped threads.
+ * Every thread works on its own copy of the board, move generator and perft table are shared.
+ * Result is in move generation order, exactly as in the sequential version.
+ */
+pub fn perft_divide_parallel(
+    board: &Board,
+    mg: &MoveGenerator,
+    depth: i8,
+    table: Option<&PerftTable>,
+    threads: usize,
+) -> Vec<(Move, u64)> {
+    if depth <= 0 {
+        return vec![];
     }
 
-    println!("Total time spent: {total_time} ms");
-    println!("Total nodes: {total_nodes} nodes");
+    let mut move_list = MoveList::new();
+    mg.generate_legal_moves(board, &mut move_list);
+
+    let root_moves: Vec<Move> = move_list.into_iter().collect();
+    let next_move = AtomicUsize::new(0);
+
+    let mut counts: Vec<(usize, u64)> = thread::scope(|s| {
+        let workers: Vec<_> = (0..threads.clamp(1, root_moves.len().max(1)))
+            .map(|_| {
+                let mut thread_board = board.clone();
+                let (root_moves, next_move) = (&root_moves, &next_move);
+
+                s.spawn(move || {
+                    let mut counts = vec![];
+                    loop {
+                        let index = next_move.fetch_add(1, Ordering::Relaxed);
+                        let Some(move_data) = root_moves.get(index) else {
+                            break;
+                        };
+
+                        thread_board.make_legal_move(*move_data);
+                        counts.push((index, perft_bulk(&mut thread_board, mg, depth - 1, table)));
+                        thread_board.unmake_move();
+                    }
+                    counts
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("Perft thread panicked"))
+            .collect()
+    });
+    counts.sort_by_key(|(index, _)| *index);
+
+    counts
+        .into_iter()
+        .map(|(index, nodes)| (root_moves[index], nodes))
+        .collect()
 }
 
-fn perft(board: &mut Board, mg: &MoveGenerator, depth: i8) -> u64 {
+// Reference perft: pseudo legal moves are made and checked on the board, including the ones at the last ply.
+pub fn perft(board: &mut Board, mg: &MoveGenerator, depth: i8) -> u64 {
     let mut nodes = 0u64;
     let mut move_list = MoveList::new();
 
@@ -64,3 +148,160 @@ fn perft(board: &mut Board, mg: &MoveGenerator, depth: i8) -> u64 {
 
     nodes
 }
+
+/*
+ * Fast perft.
+ * Moves come from the legal move generator, so at depth 1 they are only counted (bulk counting)
+ * instead of being made on the board.
+ * Optionally subtree counts are cached in the perft table keyed by zobrist key and depth,
+ * so transpositions are counted only once.
+ */
+pub fn perft_bulk(
+    board: &mut Board,
+    mg: &MoveGenerator,
+    depth: i8,
+    table: Option<&PerftTable>,
+) -> u64 {
+    if depth <= 0 {
+        return 1;
+    }
+
+    if let Some(nodes) = table.and_then(|t| t.probe(board.zobrist_key(), depth)) {
+        return nodes;
+    }
+
+    let mut move_list = MoveList::new();
+    mg.generate_legal_moves(board, &mut move_list);
+
+    let nodes = if depth == 1 {
+        move_list.count as u64
+    } else {
+        let mut nodes = 0u64;
+        for move_data in move_list {
+            board.make_legal_move(move_data);
+            nodes += perft_bulk(board, mg, depth - 1, table);
+
+            board.unmake_move();
+        }
+        nodes
+    };
+
+    if let Some(table) = table {
+        table.store(board.zobrist_key(), depth, nodes);
+    }
+
+    nodes
+}
+
+pub const DEFAULT_PERFT_TABLE_SIZE_MB: usize = 128;
+
+#[derive(Default)]
+struct PerftSlot {
+    key: AtomicU64,
+    nodes: AtomicU64,
+}
+
+/*
+ * Hash table of perft subtree counts. It is lock-free in the same way as the transposition table:
+ * key is stored XOR-ed with the count, so torn writes from different threads are detected on probe.
+ */
+pub struct PerftTable {
+    slots: Vec<PerftSlot>,
+}
+
+impl Default for PerftTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_PERFT_TABLE_SIZE_MB)
+    }
+}
+
+impl PerftTable {
+    pub fn new(size_mb: usize) -> Self {
+        let slot_count = (size_mb.max(1) * 1024 * 1024 / std::mem::size_of::<PerftSlot>()).max(1);
+        let mut slots = Vec::with_capacity(slot_count);
+        slots.resize_with(slot_count, PerftSlot::default);
+
+        Self { slots }
+    }
+
+    // Same position at different depths has different counts, so depth is mixed into the key
+    fn entry_key(zobrist_key: u64, depth: i8) -> u64 {
+        zobrist_key ^ (depth as u64).wrapping_mul(0x9e3779b97f4a7c15)
+    }
+
+    fn slot(&self, key: u64) -> &PerftSlot {
+        &self.slots[(key % self.slots.len() as u64) as usize]
+    }
+
+    pub fn probe(&self, zobrist_key: u64, depth: i8) -> Option<u64> {
+        let key = Self::entry_key(zobrist_key, depth);
+        let slot = self.slot(key);
+        let nodes = slot.nodes.load(Ordering::Relaxed);
+
+        (slot.key.load(Ordering::Relaxed) ^ nodes == key && nodes != 0).then_some(nodes)
+    }
+
+    pub fn store(&self, zobrist_key: u64, depth: i8, nodes: u64) {
+        let key = Self::entry_key(zobrist_key, depth);
+        let slot = self.slot(key);
+
+        slot.key.store(key ^ nodes, Ordering::Relaxed);
+        slot.nodes.store(nodes, Ordering::Relaxed);
+    }
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board_repr::fen::Fen, move_generation::generator::MoveGenerator, _EN_PASSANT, _POSITION_3,
+        _POSITION_4, _START_FEN, _TRICKY_POSITION,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_perft_divide() {
+        let mg = MoveGenerator::shared();
+        let mut board = Fen::to_board(_START_FEN);
+
+        let divide = perft_divide(&mut board, mg, 2, None);
+
+        assert_eq!(divide.len(), 20);
+        assert!(divide.iter().all(|(_, nodes)| *nodes == 20));
+        assert_eq!(perft(&mut board, mg, 3), 8902);
+    }
+
+    #[test]
+    fn test_perft_divide_parallel_matches_sequential() {
+        let mg = MoveGenerator::shared();
+        let mut board = Fen::to_board(_TRICKY_POSITION);
+
+        let sequential = perft_divide(&mut board, mg, 3, None);
+        let parallel = perft_divide_parallel(&board, mg, 3, None, 4);
+
+        assert_eq!(sequential.len(), parallel.len());
+        for ((m1, n1), (m2, n2)) in sequential.iter().zip(parallel.iter()) {
+            assert!(m1 == m2);
+            assert_eq!(n1, n2);
+        }
+    }
+
+    #[test]
+    fn test_perft_bulk_matches_reference() {
+        let mg = MoveGenerator::shared();
+        let table = PerftTable::new(1);
+
+        for fen in [_TRICKY_POSITION, _POSITION_3, _POSITION_4, _EN_PASSANT] {
+            let mut board = Fen::to_board(fen);
+            let expected = perft(&mut board, mg, 3);
+
+            assert_eq!(perft_bulk(&mut board, mg, 3, None), expected);
+            assert_eq!(perft_bulk(&mut board, mg, 3, Some(&table)), expected);
+            // Second run is served from the table
+            assert_eq!(perft_bulk(&mut board, mg, 3, Some(&table)), expected);
+        }
+    }
+}
@@ -0,0 +1,352 @@
+/*
+ * Perft suite runner.
+ * Suite is stored as EPD lines with expected perft counts for every depth:
+ *
+ * rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ;D1 20 ;D2 400 ;D3 8902
+ *
+ * If the count does not match, divide at the failing depth is reported,
+ * so it can be diffed against the divide of a reference engine.
+ */
+
+use std::fmt::Display;
+
+use crate::{
+    board_repr::{epd::Epd, fen::Fen},
+    move_generation::{generator::MoveGenerator, moves::Move},
+    _PERFT_POSITION_5, _POSITION_3, _POSITION_4, _POSITION_6, _START_FEN, _TRICKY_POSITION,
+};
+
+use super::perft::{perft_bulk, perft_divide, PerftTable};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PerftCase {
+    pub fen: String,
+    pub expected: Vec<(i8, u64)>,
+}
+
+impl PerftCase {
+    pub fn new(fen: &str, expected: &[u64]) -> Self {
+        Self {
+            fen: fen.to_string(),
+            expected: expected
+                .iter()
+                .enumerate()
+                .map(|(i, nodes)| (i as i8 + 1, *nodes))
+                .collect(),
+        }
+    }
+
+    // Parses "fen ;D1 20 ;D2 400 ..." line. Returns None for empty lines and comments.
+    pub fn parse(line: &str) -> Result<Option<Self>, String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+
+        let epd = Epd::parse(line, MoveGenerator::shared())?;
+        let fen = line.split(';').next().unwrap_or("").trim().to_string();
+        let expected = epd.perft_counts();
+        let depth_operations = epd.operations.iter().filter(|o| o.opcode.starts_with('D'));
+        if expected.is_empty() || expected.len() != depth_operations.count() {
+            return Err(format!("Invalid perft counts: {line}"));
+        }
+
+        Ok(Some(Self { fen, expected }))
+    }
+}
+
+// Bad line fails the whole suite, so that a typo does not silently drop a case
+pub fn parse_perft_suite(text: &str) -> Result<Vec<PerftCase>, String> {
+    let mut cases = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let case = PerftCase::parse(line).map_err(|e| format!("Line {}: {e}", i + 1))?;
+        cases.extend(case);
+    }
+    Ok(cases)
+}
+
+// Well known positions from https://www.chessprogramming.org/Perft_Results
+pub fn standard_perft_suite() -> Vec<PerftCase> {
+    vec![
+        PerftCase::new(
+            _START_FEN,
+            &[20, 400, 8_902, 197_281, 4_865_609, 119_060_324],
+        ),
+        PerftCase::new(
+            _TRICKY_POSITION,
+            &[48, 2_039, 97_862, 4_085_603, 193_690_690, 8_031_647_685],
+        ),
+        PerftCase::new(
+            _POSITION_3,
+            &[14, 191, 2_812, 43_238, 674_624, 11_030_083, 178_633_661],
+        ),
+        PerftCase::new(
+            _POSITION_4,
+            &[6, 264, 9_467, 422_333, 15_833_292, 706_045_033],
+        ),
+        PerftCase::new(
+            _PERFT_POSITION_5,
+            &[44, 1_486, 62_379, 2_103_487, 89_941_194],
+        ),
+        PerftCase::new(
+            _POSITION_6,
+            &[46, 2_079, 89_890, 3_894_594, 164_075_551, 6_923_051_137],
+        ),
+    ]
+}
+
+// Chess960 positions from https://www.chessprogramming.org/Chess960_Perft_Results
+pub fn chess960_perft_suite() -> Vec<PerftCase> {
+    vec![
+        PerftCase::new(
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+            &[21, 528, 12_189, 326_672, 8_146_062, 227_689_589],
+        ),
+        PerftCase::new(
+            "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+            &[21, 807, 18_002, 667_366, 16_253_601, 590_751_109],
+        ),
+        PerftCase::new(
+            "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+            &[20, 479, 10_471, 273_318, 6_417_013, 177_654_692],
+        ),
+        PerftCase::new(
+            "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+            &[22, 593, 13_440, 382_958, 9_183_776, 274_103_539],
+        ),
+        PerftCase::new(
+            "1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9",
+            &[28, 1_120, 31_058, 1_171_749, 34_030_312, 1_250_970_898],
+        ),
+        PerftCase::new(
+            "qnbnr1kr/ppp1b1pp/4p3/3p1p2/8/2NPP3/PPP1BPPP/QNB1R1KR w HEhe - 1 9",
+            &[29, 899, 26_578, 824_055, 24_851_983, 775_718_317],
+        ),
+        PerftCase::new(
+            "q1bnrkr1/ppppp2p/2n2p2/4b1p1/2NP4/8/PPP1PPPP/QNB1RRKB w ge - 1 9",
+            &[30, 860, 24_566, 732_757, 21_093_346, 649_209_803],
+        ),
+        PerftCase::new(
+            "qbn1brkr/ppp1p1p1/2n4p/3p1p2/P7/6PP/QPPPPP2/1BNNBRKR w HFhf - 0 9",
+            &[25, 635, 17_054, 465_806, 13_203_304, 377_184_252],
+        ),
+        PerftCase::new(
+            "qnnbbrkr/1p2ppp1/2pp3p/p7/1P5P/2NP4/P1P1PPP1/Q1NBBRKR w HFhf - 0 9",
+            &[24, 572, 15_243, 384_260, 11_110_203, 293_989_890],
+        ),
+        PerftCase::new(
+            "qn1rbbkr/ppp2p1p/1n1pp1p1/8/3P4/P6P/1PP1PPPK/QNNRBB1R w hd - 2 9",
+            &[28, 811, 23_175, 679_699, 19_836_606, 594_527_992],
+        ),
+    ]
+}
+
+#[derive(Debug)]
+pub struct PerftMismatch {
+    pub fen: String,
+    pub depth: i8,
+    pub expected: u64,
+    pub actual: u64,
+    pub divide: Vec<(Move, u64)>,
+}
+
+impl Display for PerftMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Perft mismatch: {}", self.fen)?;
+        writeln!(
+            f,
+            "Depth {}: expected {}, got {} ({:+})",
+            self.depth,
+            self.expected,
+            self.actual,
+            self.actual as i128 - self.expected as i128
+        )?;
+        writeln!(f, "Divide:")?;
+        for (move_data, nodes) in self.divide.iter() {
+            writeln!(f, "{}: {nodes}", move_data.to_uci_string())?;
+        }
+
+        Ok(())
+    }
+}
+
+// Checks every depth up to 'max_depth', stops on the first mismatch.
+pub fn run_perft_case(
+    case: &PerftCase,
+    mg: &MoveGenerator,
+    max_depth: i8,
+    table: &PerftTable,
+) -> Result<(), PerftMismatch> {
+    let mut board = Fen::to_board(&case.fen);
+
+    for (depth, expected) in case.expected.iter().filter(|(d, _)| *d <= max_depth) {
+        let actual = perft_bulk(&mut board, mg, *depth, Some(table));
+
+        if actual != *expected {
+            return Err(PerftMismatch {
+                fen: case.fen.clone(),
+                depth: *depth,
+                expected: *expected,
+                actual,
+                divide: perft_divide(&mut board, mg, *depth, None),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+pub fn run_perft_suite(
+    cases: &[PerftCase],
+    mg: &MoveGenerator,
+    max_depth: i8,
+) -> Vec<PerftMismatch> {
+    let mut mismatches = vec![];
+    // Keys of different positions do not clash, so one table serves the whole suite
+    let table = PerftTable::default();
+
+    for (i, case) in cases.iter().enumerate() {
+        match run_perft_case(case, mg, max_depth, &table) {
+            Ok(()) => println!("{}/{} OK: {}", i + 1, cases.len(), case.fen),
+            Err(mismatch) => {
+                println!("{}/{} FAILED", i + 1, cases.len());
+                println!("{mismatch}");
+                mismatches.push(mismatch);
+            }
+        }
+    }
+
+    println!();
+    println!(
+        "Passed {} of {} positions",
+        cases.len() - mismatches.len(),
+        cases.len()
+    );
+
+    mismatches
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_standard_case(index: usize, max_depth: i8) {
+        let mg = MoveGenerator::shared();
+        let case = &standard_perft_suite()[index];
+        let table = PerftTable::new(16);
+
+        if let Err(mismatch) = run_perft_case(case, mg, max_depth, &table) {
+            panic!("{mismatch}");
+        }
+    }
+
+    #[test]
+    fn test_parse_perft_line() {
+        let case = PerftCase::parse("8/8/8/8/8/8/8/K6k w - - ;D1 3 ;D2 9")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(case.fen, "8/8/8/8/8/8/8/K6k w - -");
+        assert_eq!(case.expected, vec![(1, 3), (2, 9)]);
+        assert_eq!(PerftCase::parse("# comment"), Ok(None));
+        assert_eq!(PerftCase::parse("   "), Ok(None));
+
+        // Typos are reported instead of dropping the case
+        assert!(PerftCase::parse("8/8/8/8/8/8/8/K6x w - - ;D1 3").is_err());
+        assert!(PerftCase::parse("8/8/8/8/8/8/8/K6k w - - ;D1 3 ;D2 nine").is_err());
+        let error = parse_perft_suite(
+            "# suite\n8/8/8/8/8/8/8/K6k w - - ;D1 3\n8/8/9/8/8/8/8/K6k w - - ;D1 3",
+        );
+        assert!(error.unwrap_err().starts_with("Line 3"));
+    }
+
+    #[test]
+    fn test_perft_start_position() {
+        run_standard_case(0, 3);
+    }
+
+    #[test]
+    fn test_perft_tricky_position() {
+        run_standard_case(1, 3);
+    }
+
+    #[test]
+    fn test_perft_position_3() {
+        run_standard_case(2, 4);
+    }
+
+    #[test]
+    fn test_perft_position_4() {
+        run_standard_case(3, 3);
+    }
+
+    #[test]
+    fn test_perft_position_5() {
+        run_standard_case(4, 3);
+    }
+
+    #[test]
+    fn test_perft_position_6() {
+        run_standard_case(5, 3);
+    }
+
+    #[test]
+    fn test_perft_chess960() {
+        let mg = MoveGenerator::shared();
+        let table = PerftTable::new(16);
+
+        for case in chess960_perft_suite() {
+            if let Err(mismatch) = run_perft_case(&case, mg, 4, &table) {
+                panic!("{mismatch}");
+            }
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_perft_chess960_deep() {
+        let mg = MoveGenerator::shared();
+        let mismatches = run_perft_suite(&chess960_perft_suite(), mg, 6);
+
+        assert!(mismatches.is_empty());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_perft_start_position_deep() {
+        run_standard_case(0, 6);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_perft_tricky_position_deep() {
+        run_standard_case(1, 6);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_perft_position_3_deep() {
+        run_standard_case(2, 7);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_perft_position_4_deep() {
+        run_standard_case(3, 6);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_perft_position_5_deep() {
+        run_standard_case(4, 5);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_perft_position_6_deep() {
+        run_standard_case(5, 6);
+    }
+}
@@ -0,0 +1,775 @@
+/*
+ * PGN reader and writer.
+ * Reader accepts any number of games per text: tag pairs, SAN movetext with move numbers,
+ * brace and rest-of-line comments, NAGs (including '!' and '?' suffixes), nested variations
+ * and game termination markers. Moves are resolved with the SAN parser and applied through
+ * 'Board::make_move', so an illegal move or a malformed 'FEN' tag fails the whole text.
+ * Writer puts the seven tag roster first and wraps movetext at 'PGN_LINE_WIDTH' characters.
+ */
+
+use std::io::BufRead;
+
+use crate::{
+    board_repr::{board::Board, fen::Fen, piece::Color},
+    move_generation::{generator::MoveGenerator, moves::Move, san::San},
+    _START_FEN,
+};
+
+pub const PGN_LINE_WIDTH: usize = 80;
+pub const SEVEN_TAG_ROSTER: [&str; 7] =
+    ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+// Move suffix annotations and the NAGs they stand for
+const SUFFIX_NAGS: [(&str, u8); 6] = [
+    ("!!", 3),
+    ("??", 4),
+    ("!?", 5),
+    ("?!", 6),
+    ("!", 1),
+    ("?", 2),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnMove {
+    pub move_data: Move,
+    pub nags: Vec<u8>,
+    // Comment before the move, only the first move of a line can have one
+    pub starting_comment: Option<String>,
+    // Comment following the move
+    pub comment: Option<String>,
+    // Alternatives to this move, each played from the position before it
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+    pub fn new(move_data: Move) -> Self {
+        Self {
+            move_data,
+            nags: vec![],
+            starting_comment: None,
+            comment: None,
+            variations: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    // Comment before the first move
+    pub comment: Option<String>,
+    pub moves: Vec<PgnMove>,
+    pub result: String,
+}
+
+impl Default for PgnGame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PgnGame {
+    // Game with unknown seven tag roster values and no moves
+    pub fn new() -> Self {
+        let tags = SEVEN_TAG_ROSTER
+            .iter()
+            .map(|name| {
+                let value = match *name {
+                    "Date" => "????.??.??",
+                    "Result" => "*",
+                    _ => "?",
+                };
+                (name.to_string(), value.to_string())
+            })
+            .collect();
+
+        Self {
+            tags,
+            comment: None,
+            moves: vec![],
+            result: String::from("*"),
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old_value)) => *old_value = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn set_result(&mut self, result: &str) {
+        self.result = result.to_string();
+        self.set_tag("Result", result);
+    }
+
+    // Position the game starts from, taken from the 'FEN' tag if there is one
+    pub fn start_board(&self) -> Result<Board, String> {
+        Fen::try_to_board(self.tag("FEN").unwrap_or(_START_FEN))
+            .map_err(|e| format!("Invalid FEN tag: {e}"))
+    }
+
+    pub fn mainline(&self) -> Vec<Move> {
+        self.moves.iter().map(|m| m.move_data).collect()
+    }
+
+    // Position after the last mainline move
+    pub fn board(&self, mg: &MoveGenerator) -> Result<Board, String> {
+        let mut board = self.start_board()?;
+        for move_data in self.mainline() {
+            board.make_move(move_data, mg);
+        }
+        Ok(board)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    VariationStart,
+    VariationEnd,
+    Result(String),
+    San(String),
+}
+
+#[derive(Debug)]
+pub struct Pgn;
+
+impl Pgn {
+    pub fn parse(text: &str, mg: &MoveGenerator) -> Result<Vec<PgnGame>, String> {
+        let tokens = Self::tokenize(text)?;
+        let mut games = vec![];
+        let mut index = 0;
+
+        while index < tokens.len() {
+            let mut game = PgnGame {
+                tags: vec![],
+                ..PgnGame::new()
+            };
+
+            while let Some(Token::Tag(name, value)) = tokens.get(index) {
+                game.tags.push((name.clone(), value.clone()));
+                index += 1;
+            }
+
+            if let Some(Token::Comment(comment)) = tokens.get(index) {
+                game.comment = Some(comment.clone());
+                index += 1;
+            }
+
+            let mut board = game.start_board()?;
+            board.validate(mg)?;
+            game.moves = Self::parse_line(&tokens, &mut index, &mut board, mg, false)?;
+
+            let result = match tokens.get(index) {
+                Some(Token::Result(result)) => {
+                    index += 1;
+                    result.clone()
+                }
+                // Next game started or text ended without termination marker
+                _ => game.tag("Result").unwrap_or("*").to_string(),
+            };
+            game.result = result.clone();
+            if game.tag("Result").is_none() {
+                game.tags.push((String::from("Result"), result));
+            }
+
+            // Comments after the result belong to no game, unless moves of a tagless game follow
+            let mut next = index;
+            while let Some(Token::Comment(_)) = tokens.get(next) {
+                next += 1;
+            }
+            if matches!(tokens.get(next), None | Some(Token::Tag(..))) {
+                index = next;
+            }
+
+            games.push(game);
+        }
+
+        Ok(games)
+    }
+
+    // Parses moves until the end of the variation, game termination marker or the next game's tags
+    fn parse_line(
+        tokens: &[Token],
+        index: &mut usize,
+        board: &mut Board,
+        mg: &MoveGenerator,
+        is_variation: bool,
+    ) -> Result<Vec<PgnMove>, String> {
+        let mut moves: Vec<PgnMove> = vec![];
+        let mut starting_comment: Option<String> = None;
+
+        while let Some(token) = tokens.get(*index) {
+            match token {
+                Token::San(san) => {
+                    let move_data = San::parse_move(san, board, mg)
+                        .ok_or_else(|| format!("Illegal or ambiguous move: {san}"))?;
+                    if !board.make_move(move_data, mg) {
+                        return Err(format!("Illegal move: {san}"));
+                    }
+                    moves.push(PgnMove {
+                        starting_comment: starting_comment.take(),
+                        ..PgnMove::new(move_data)
+                    });
+                }
+                Token::Nag(nag) => {
+                    if let Some(last) = moves.last_mut() {
+                        last.nags.push(*nag);
+                    }
+                }
+                Token::Comment(comment) => {
+                    // Comment before the first move of the line is kept until that move is read
+                    let target = match moves.last_mut() {
+                        Some(last) => &mut last.comment,
+                        None => &mut starting_comment,
+                    };
+                    *target = match target.take() {
+                        Some(previous) => Some(format!("{previous} {comment}")),
+                        None => Some(comment.clone()),
+                    };
+                }
+                Token::VariationStart => {
+                    let Some(last) = moves.last_mut() else {
+                        return Err(String::from("Variation before the first move"));
+                    };
+
+                    // Variation replaces the last move, so it is played from the position before it
+                    *index += 1;
+                    board.unmake_move();
+                    let variation = Self::parse_line(tokens, index, board, mg, true)?;
+                    board.make_move(last.move_data, mg);
+                    last.variations.push(variation);
+                }
+                Token::VariationEnd => {
+                    if !is_variation {
+                        return Err(String::from("Unexpected ')'"));
+                    }
+                    for _ in 0..moves.len() {
+                        board.unmake_move();
+                    }
+                    return Ok(moves);
+                }
+                Token::Result(_) | Token::Tag(..) => {
+                    if is_variation {
+                        return Err(String::from("Unterminated variation"));
+                    }
+                    return Ok(moves);
+                }
+            }
+            *index += 1;
+        }
+
+        if is_variation {
+            return Err(String::from("Unterminated variation"));
+        }
+        Ok(moves)
+    }
+
+    fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+        let mut tokens = vec![];
+        let mut chars = text.chars().peekable();
+        let mut line_start = true;
+
+        while let Some(c) = chars.next() {
+            // '%' in the first column escapes the whole line
+            if line_start && c == '%' {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                continue;
+            }
+            line_start = c == '\n';
+
+            match c {
+                c if c.is_whitespace() => continue,
+                '[' => {
+                    let mut name = String::new();
+                    while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '"') {
+                        name.push(c);
+                    }
+                    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                    if chars.next() != Some('"') {
+                        return Err(format!("Tag {name} has no value"));
+                    }
+
+                    let mut value = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('\\') => value.extend(chars.next()),
+                            Some('"') => break,
+                            Some(c) => value.push(c),
+                            None => return Err(format!("Unterminated tag {name}")),
+                        }
+                    }
+                    while chars.next_if(|c| *c != ']').is_some() {}
+                    chars.next();
+
+                    tokens.push(Token::Tag(name, value));
+                }
+                '{' => {
+                    let mut comment = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => comment.push(c),
+                            None => return Err(String::from("Unterminated comment")),
+                        }
+                    }
+                    tokens.push(Token::Comment(comment.trim().to_string()));
+                }
+                ';' => {
+                    let mut comment = String::new();
+                    while let Some(c) = chars.next_if(|c| *c != '\n') {
+                        comment.push(c);
+                    }
+                    tokens.push(Token::Comment(comment.trim().to_string()));
+                }
+                '$' => {
+                    let mut nag = String::new();
+                    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+                        nag.push(c);
+                    }
+                    let nag = nag.parse().map_err(|_| format!("Invalid NAG: ${nag}"))?;
+                    tokens.push(Token::Nag(nag));
+                }
+                '(' => tokens.push(Token::VariationStart),
+                ')' => tokens.push(Token::VariationEnd),
+                _ => {
+                    let mut symbol = String::from(c);
+                    while let Some(c) =
+                        chars.next_if(|c| !c.is_whitespace() && !"{}()[];$".contains(*c))
+                    {
+                        symbol.push(c);
+                    }
+                    Self::push_symbol(&symbol, &mut tokens);
+                }
+            }
+        }
+
+        Ok(tokens)
+    }
+
+    // Symbol is a result, a move number, a move (possibly glued to its number) or a suffix annotation
+    fn push_symbol(symbol: &str, tokens: &mut Vec<Token>) {
+        // En-passant mark written apart from its move, 'exd6 e.p.'
+        if symbol == "e.p." {
+            return;
+        }
+
+        if RESULTS.contains(&symbol) {
+            tokens.push(Token::Result(symbol.to_string()));
+            return;
+        }
+
+        let mut san = symbol.trim_start_matches(|c: char| c.is_ascii_digit());
+        if san.starts_with('.') {
+            san = san.trim_start_matches('.');
+        } else {
+            // Digits were not a move number, e.g. '0-0'
+            san = symbol;
+        }
+
+        let mut nag = None;
+        for (suffix, suffix_nag) in SUFFIX_NAGS {
+            if let Some(stripped) = san.strip_suffix(suffix) {
+                san = stripped;
+                nag = Some(suffix_nag);
+                break;
+            }
+        }
+
+        if !san.is_empty() {
+            tokens.push(Token::San(san.to_string()));
+        }
+        if let Some(nag) = nag {
+            tokens.push(Token::Nag(nag));
+        }
+    }
+
+    pub fn write(games: &[PgnGame], mg: &MoveGenerator) -> Result<String, String> {
+        let games = games
+            .iter()
+            .map(|game| Self::write_game(game, mg))
+            .collect::<Result<Vec<String>, String>>()?;
+        Ok(games.join("\n"))
+    }
+
+    pub fn write_game(game: &PgnGame, mg: &MoveGenerator) -> Result<String, String> {
+        let mut pgn = String::new();
+
+        // Seven tag roster always comes first and in its order, other tags follow as they are
+        for name in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => game.result.as_str(),
+                _ => game.tag(name).unwrap_or("?"),
+            };
+            pgn.push_str(&format!("[{name} \"{}\"]\n", escape(value)));
+        }
+        for (name, value) in &game.tags {
+            if !SEVEN_TAG_ROSTER.contains(&name.as_str()) {
+                pgn.push_str(&format!("[{name} \"{}\"]\n", escape(value)));
+            }
+        }
+        pgn.push('\n');
+
+        let mut words = vec![];
+        if let Some(comment) = &game.comment {
+            push_comment(comment, &mut words);
+        }
+        let mut board = game.start_board()?;
+        Self::write_line(&game.moves, &mut board, mg, &mut words);
+        words.push(game.result.clone());
+
+        // Wrap movetext, words are never split
+        let mut line = String::new();
+        for word in words {
+            if !line.is_empty() && line.len() + 1 + word.len() > PGN_LINE_WIDTH {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&word);
+        }
+        pgn.push_str(&line);
+        pgn.push('\n');
+
+        Ok(pgn)
+    }
+
+    fn write_line(
+        moves: &[PgnMove],
+        board: &mut Board,
+        mg: &MoveGenerator,
+        words: &mut Vec<String>,
+    ) {
+        // Black move needs its number at the start of a line and after comments or variations
+        let mut needs_number = true;
+
+        for pgn_move in moves {
+            if let Some(comment) = &pgn_move.starting_comment {
+                push_comment(comment, words);
+                needs_number = true;
+            }
+
+            let move_number = board.game_state.fullmove_number;
+            match board.active_color() {
+                Color::White => words.push(format!("{move_number}.")),
+                _ if needs_number => words.push(format!("{move_number}...")),
+                _ => (),
+            }
+            words.push(San::from_move(pgn_move.move_data, board, mg));
+            needs_number = false;
+
+            for nag in &pgn_move.nags {
+                words.push(format!("${nag}"));
+            }
+            if let Some(comment) = &pgn_move.comment {
+                push_comment(comment, words);
+                needs_number = true;
+            }
+
+            // Parentheses stick to the first and last word of the variation
+            for variation in &pgn_move.variations {
+                let mut variation_words = vec![];
+                Self::write_line(variation, board, mg, &mut variation_words);
+                wrap_words(&mut variation_words, '(', ')');
+                words.extend(variation_words);
+                needs_number = true;
+            }
+
+            board.make_move(pgn_move.move_data, mg);
+        }
+
+        for _ in moves {
+            board.unmake_move();
+        }
+    }
+}
+
+/*
+ * Splits a PGN stream into the texts of single games, so a large file is never read as a whole
+ * and a broken game can be skipped without losing the others. A game ends where the tag section
+ * of the next one starts. Tagless games that follow each other stay in one text.
+ */
+pub struct PgnReader<R: BufRead> {
+    reader: R,
+    // Tag line of the next game, read while looking for the end of the current one
+    next_line: Option<String>,
+    in_comment: bool,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            next_line: None,
+            in_comment: false,
+        }
+    }
+
+    // Tracks brace comments spanning lines, '[' inside of them does not start a game
+    fn update_comment_state(&mut self, line: &str) {
+        for c in line.chars() {
+            match c {
+                '{' if !self.in_comment => self.in_comment = true,
+                '}' if self.in_comment => self.in_comment = false,
+                ';' if !self.in_comment => break,
+                _ => (),
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = std::io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut text = self.next_line.take().unwrap_or_default();
+        let mut has_movetext = false;
+
+        loop {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(e) => return Some(Err(e)),
+            }
+
+            let trimmed = line.trim_start();
+            let is_tag = !self.in_comment && trimmed.starts_with('[');
+            if is_tag && has_movetext {
+                self.next_line = Some(line);
+                break;
+            }
+            if !is_tag && !trimmed.is_empty() && !line.starts_with('%') {
+                has_movetext = true;
+            }
+
+            self.update_comment_state(&line);
+            text.push_str(&line);
+        }
+
+        match text.trim().is_empty() {
+            true => None,
+            false => Some(Ok(text)),
+        }
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Comment is split into words, so it can be wrapped like the rest of movetext
+fn push_comment(comment: &str, words: &mut Vec<String>) {
+    let mut comment_words: Vec<String> = comment.split_whitespace().map(String::from).collect();
+    wrap_words(&mut comment_words, '{', '}');
+    words.extend(comment_words);
+}
+
+fn wrap_words(words: &mut Vec<String>, open: char, close: char) {
+    if words.is_empty() {
+        words.push(String::new());
+    }
+
+    let last = words.len() - 1;
+    words[0].insert(0, open);
+    words[last].push(close);
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAMES: &str = r#"[Event "Casual \"blitz\""]
+[Site "?"]
+[Date "2024.01.01"]
+[Round "1"]
+[White "Engine"]
+[Black "Human"]
+[Result "1-0"]
+[ECO "C20"]
+
+{Opening comment} 1. e4 e5 2. Nf3!? (2. Qh5 Nc6 3. Bc4 Nf6?? 4. Qxf7# {Scholar's mate}
+(4. Qf3 $6)) 2... Nc6 $1 ; rest of line comment
+3. Bb5 a6
+% escaped line 4. d4
+4. Ba4 Nf6 5. O-O 1-0
+
+[Event "Second"]
+[FEN "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"]
+[SetUp "1"]
+
+1.Ra8# *
+"#;
+
+    #[test]
+    fn test_parse_multiple_games() {
+        let mg = MoveGenerator::shared();
+        let games = Pgn::parse(GAMES, mg).unwrap();
+        assert_eq!(games.len(), 2);
+
+        let game = &games[0];
+        assert_eq!(game.tag("Event"), Some("Casual \"blitz\""));
+        assert_eq!(game.tag("ECO"), Some("C20"));
+        assert_eq!(game.result, "1-0");
+        assert_eq!(game.comment.as_deref(), Some("Opening comment"));
+
+        let moves: Vec<String> = game.mainline().iter().map(|m| m.to_uci_string()).collect();
+        assert_eq!(
+            moves,
+            ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "a7a6", "b5a4", "g8f6", "e1g1"]
+        );
+
+        // 'Nf3!?' and its variation with a nested one
+        let knight = &game.moves[2];
+        assert_eq!(knight.nags, [5]);
+        assert_eq!(knight.variations.len(), 1);
+        let variation = &knight.variations[0];
+        assert_eq!(variation.len(), 5);
+        assert_eq!(variation[3].nags, [4]);
+        assert_eq!(variation[4].comment.as_deref(), Some("Scholar's mate"));
+        assert_eq!(variation[4].variations[0][0].nags, [6]);
+
+        assert_eq!(game.moves[3].nags, [1]);
+        assert_eq!(
+            game.moves[3].comment.as_deref(),
+            Some("rest of line comment")
+        );
+
+        let game = &games[1];
+        assert_eq!(game.result, "*");
+        assert_eq!(game.mainline()[0].to_uci_string(), "a1a8");
+        assert_eq!(game.tag("Result"), Some("*"));
+    }
+
+    #[test]
+    fn test_write_and_read_back() {
+        let mg = MoveGenerator::shared();
+        let games = Pgn::parse(GAMES, mg).unwrap();
+        let pgn = Pgn::write(&games, mg).unwrap();
+        assert!(pgn.lines().all(|line| line.len() <= PGN_LINE_WIDTH));
+
+        let pgn_text = pgn.replace('\n', " ");
+        assert!(pgn_text.contains("[Event \"Casual \\\"blitz\\\"\"]"));
+        assert!(pgn_text.contains(
+            "2. Nf3 $5 (2. Qh5 Nc6 3. Bc4 Nf6 $4 4. Qxf7# {Scholar's mate} (4. Qf3 $6))"
+        ));
+        assert!(pgn_text.contains("2... Nc6 $1 {rest of line comment} 3. Bb5"));
+        assert!(pgn_text.contains("1. Ra8# *"));
+
+        // Second game gets the missing roster tags, so only the first one reads back unchanged
+        let read_back = Pgn::parse(&pgn, mg).unwrap();
+        assert_eq!(read_back[0], games[0]);
+        assert_eq!(read_back[1].moves, games[1].moves);
+        assert_eq!(read_back[1].tag("Site"), Some("?"));
+    }
+
+    #[test]
+    fn test_long_game_is_wrapped() {
+        let mg = MoveGenerator::shared();
+        let mut text = String::new();
+        for _ in 0..10 {
+            text.push_str("Nf3 Nf6 Ng1 Ng8 ");
+        }
+        let games = Pgn::parse(&text, mg).unwrap();
+        assert_eq!(games[0].moves.len(), 40);
+        assert_eq!(
+            games[0].board(mg).unwrap().zobrist_key(),
+            games[0].start_board().unwrap().zobrist_key()
+        );
+
+        let pgn = Pgn::write_game(&games[0], mg).unwrap();
+        let movetext: Vec<&str> = pgn.lines().skip(8).collect();
+        assert!(movetext.len() > 1);
+        assert!(movetext.iter().all(|line| line.len() <= PGN_LINE_WIDTH));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let mg = MoveGenerator::shared();
+        assert!(Pgn::parse("1. e5 *", mg).is_err());
+        assert!(Pgn::parse("1. e4 (1. d4 *", mg).is_err());
+        assert!(Pgn::parse("1. e4 {unterminated", mg).is_err());
+
+        // Malformed or illegal position in the FEN tag
+        assert!(Pgn::parse("[FEN \"bogus\"]\n1. e4 *", mg).is_err());
+        assert!(Pgn::parse("[FEN \"8/8/8/8/8/8/4P3/4K3 w - - 0 1\"]\n1. e4 *", mg).is_err());
+    }
+
+    #[test]
+    fn test_game_longer_than_255_moves() {
+        let mg = MoveGenerator::shared();
+        let text = "Nf3 Nf6 Ng1 Ng8 ".repeat(130);
+
+        // Neither move counter overflows, halfmove clock stops at its maximum
+        let games = Pgn::parse(&text, mg).unwrap();
+        let board = games[0].board(mg).unwrap();
+        assert_eq!(games[0].moves.len(), 520);
+        assert_eq!(board.game_state.fullmove_number, 261);
+        assert_eq!(board.game_state.halfmove_clock, u8::MAX);
+    }
+
+    #[test]
+    fn test_comments_around_variations_and_results() {
+        let mg = MoveGenerator::shared();
+        let text = "1. e4 ({Main alternative} 1. d4 {Queen pawn}) 1... d5 2. exd5 c5 3. dxc6 e.p. \
+                    1-0 {Played online}\n\n[Event \"Next\"]\n1. c4 *";
+        let games = Pgn::parse(text, mg).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[1].tag("Event"), Some("Next"));
+
+        let variation = &games[0].moves[0].variations[0];
+        assert_eq!(
+            variation[0].starting_comment.as_deref(),
+            Some("Main alternative")
+        );
+        assert_eq!(variation[0].comment.as_deref(), Some("Queen pawn"));
+        assert!(games[0].moves[4].move_data.en_passant());
+
+        let pgn = Pgn::write_game(&games[0], mg).unwrap();
+        assert!(pgn.contains("1. e4 ({Main alternative} 1. d4 {Queen pawn}) 1... d5"));
+        assert_eq!(Pgn::parse(&pgn, mg).unwrap()[0].moves, games[0].moves);
+
+        // Comment after the last result does not start a game
+        assert_eq!(Pgn::parse("1. e4 * {end}", mg).unwrap().len(), 1);
+        assert_eq!(Pgn::parse("1. e4 * {next} 1. d4 *", mg).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_reader_splits_games() {
+        let text = format!("{GAMES}\n[Event \"Third\"]\n{{[not a tag]\n[still not]}} 1. d4 *\n");
+        let games: Vec<String> = PgnReader::new(text.as_bytes())
+            .map(|game| game.unwrap())
+            .collect();
+        assert_eq!(games.len(), 3);
+        assert!(games[0].ends_with("5. O-O 1-0\n\n"));
+        assert!(games[1].starts_with("[Event \"Second\"]"));
+        assert!(games[2].contains("[still not]} 1. d4 *"));
+
+        let mg = MoveGenerator::shared();
+        let parsed: Vec<PgnGame> = games
+            .iter()
+            .flat_map(|game| Pgn::parse(game, mg).unwrap())
+            .collect();
+        assert_eq!(parsed[..2], Pgn::parse(GAMES, mg).unwrap());
+    }
+}
@@ -0,0 +1,296 @@
+/*
+ * Test suite solver.
+ * Searches every EPD position of a suite (WAC, ECM, STS...) with the same time or depth limit
+ * and checks the chosen move against 'bm' and 'am' operations:
+ *
+ * testsuite wac.epd [movetime 1000] [depth N]
+ *
+ * Time to solution is the time of the iteration from which the engine kept a correct move.
+ * Positions with neither 'bm' nor 'am' have nothing to solve and are reported as unscored.
+ * STS positions carry points for several moves, either as 'c8' points with 'c9' UCI moves
+ * or as a 'c0' list like "Nf3=10, Nd2=7", and the report adds them up (10 per position at most).
+ */
+
+use std::time::Duration;
+
+use crate::{
+    board_repr::epd::Epd,
+    move_generation::{generator::MoveGenerator, moves::Move, san::San},
+    search::{
+        smp::{lazy_smp, SearchLimits},
+        transposition::TranspositionTable,
+    },
+};
+
+pub const DEFAULT_TEST_SUITE_MOVETIME: Duration = Duration::from_millis(1000);
+pub const STS_MAX_POINTS: u32 = 10;
+
+#[derive(Debug, Clone)]
+pub struct TestPositionResult {
+    pub id: String,
+    pub best_move: Option<Move>,
+    // Position has 'bm' or 'am', so the chosen move can be checked
+    pub scored: bool,
+    pub solved: bool,
+    pub time_to_solution: Option<Duration>,
+    pub depth_to_solution: Option<i8>,
+    // STS points of the chosen move, None if the position has no points
+    pub points: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TestSuiteReport {
+    pub results: Vec<TestPositionResult>,
+    pub nodes: u64,
+    // Sum of times to solution of solved positions
+    pub solution_time: Duration,
+}
+
+impl TestSuiteReport {
+    pub fn solved(&self) -> usize {
+        self.results.iter().filter(|r| r.solved).count()
+    }
+
+    pub fn scored(&self) -> usize {
+        self.results.iter().filter(|r| r.scored).count()
+    }
+
+    pub fn points(&self) -> u32 {
+        self.results.iter().filter_map(|r| r.points).sum()
+    }
+
+    pub fn max_points(&self) -> u32 {
+        self.results.iter().filter(|r| r.points.is_some()).count() as u32 * STS_MAX_POINTS
+    }
+}
+
+// Lines starting with '#' and empty lines are skipped
+pub fn parse_test_suite(text: &str, mg: &MoveGenerator) -> Result<Vec<Epd>, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| Epd::parse(line, mg))
+        .collect()
+}
+
+pub fn is_scored(epd: &Epd) -> bool {
+    !epd.best_moves().is_empty() || !epd.avoid_moves().is_empty()
+}
+
+// Correct move has to be one of 'bm' (if given) and none of 'am', unscored positions have none
+pub fn is_solution(epd: &Epd, move_data: Move) -> bool {
+    let best_moves = epd.best_moves();
+    let avoid_moves = epd.avoid_moves();
+
+    is_scored(epd)
+        && (best_moves.is_empty() || best_moves.contains(&move_data))
+        && !avoid_moves.contains(&move_data)
+}
+
+pub fn sts_points(epd: &Epd, move_data: Move, mg: &MoveGenerator) -> Option<u32> {
+    // "c8 "10 7 5"; c9 "g1f3 b1d2 c2c3";"
+    if let (Some(points), Some(moves)) = (epd.string("c8"), epd.string("c9")) {
+        let scored = moves.split_whitespace().zip(points.split_whitespace());
+        for (uci, points) in scored {
+            if move_data.to_uci_string() == uci {
+                return points.parse().ok();
+            }
+        }
+        return Some(0);
+    }
+
+    // "c0 "Nf3=10, Nd2=7, c3=5";"
+    let list = epd.comment(0)?;
+    if !list.contains('=') {
+        return None;
+    }
+    for entry in list.split(',') {
+        let Some((san, points)) = entry.trim().rsplit_once('=') else {
+            continue;
+        };
+        if San::parse_move(san, &epd.board, mg) == Some(move_data) {
+            return points.trim().parse().ok();
+        }
+    }
+
+    Some(0)
+}
+
+pub fn run_test_position(
+    epd: &Epd,
+    mg: &MoveGenerator,
+    tt: &TranspositionTable,
+    limits: SearchLimits,
+    threads: usize,
+) -> (TestPositionResult, u64) {
+    let mut board = epd.board.clone();
+    let mut solution_found: Option<(Duration, i8)> = None;
+
+    let result = lazy_smp(
+        &mut board,
+        mg,
+        tt,
+        limits,
+        threads,
+        |iteration| match iteration.best_move {
+            Some(move_data) if is_solution(epd, move_data) => {
+                solution_found.get_or_insert((iteration.elapsed, iteration.depth));
+            }
+            _ => solution_found = None,
+        },
+    );
+
+    // Main thread iterations may disagree with the move picked from all threads
+    let solved = result.best_move.is_some_and(|m| is_solution(epd, m));
+    if !solved {
+        solution_found = None;
+    }
+
+    let position_result = TestPositionResult {
+        id: epd.id().unwrap_or("").to_string(),
+        best_move: result.best_move,
+        scored: is_scored(epd),
+        solved,
+        time_to_solution: solution_found.map(|(time, _)| time),
+        depth_to_solution: solution_found.map(|(_, depth)| depth),
+        points: result.best_move.and_then(|m| sts_points(epd, m, mg)),
+    };
+
+    (position_result, result.nodes)
+}
+
+pub fn run_test_suite(
+    suite: &[Epd],
+    mg: &MoveGenerator,
+    tt: &TranspositionTable,
+    limits: SearchLimits,
+    threads: usize,
+) -> TestSuiteReport {
+    let mut report = TestSuiteReport::default();
+
+    for (i, epd) in suite.iter().enumerate() {
+        tt.clear();
+        let (result, nodes) = run_test_position(epd, mg, tt, limits, threads);
+        report.nodes += nodes;
+
+        let chosen = result.best_move.map_or(String::from("(none)"), |m| {
+            San::from_move(m, &epd.board, mg)
+        });
+        let expected: Vec<String> = epd
+            .best_moves()
+            .iter()
+            .map(|m| San::from_move(*m, &epd.board, mg))
+            .chain(
+                epd.avoid_moves()
+                    .iter()
+                    .map(|m| format!("not {}", San::from_move(*m, &epd.board, mg))),
+            )
+            .collect();
+
+        let mut line = format!(
+            "{:>4}/{} {:<8} {:<20} {:<8} expected {}",
+            i + 1,
+            suite.len(),
+            match (result.scored, result.solved) {
+                (false, _) => "UNSCORED",
+                (_, true) => "SOLVED",
+                _ => "FAILED",
+            },
+            result.id,
+            chosen,
+            expected.join(" ")
+        );
+        if let Some(time) = result.time_to_solution {
+            line.push_str(&format!(
+                "  time {} ms depth {}",
+                time.as_millis(),
+                result.depth_to_solution.unwrap_or(0)
+            ));
+        }
+        if let Some(points) = result.points {
+            line.push_str(&format!("  points {points}"));
+        }
+        println!("{line}");
+
+        report.solution_time += result.time_to_solution.unwrap_or_default();
+        report.results.push(result);
+    }
+
+    println!();
+    println!(
+        "Solved {} of {} positions",
+        report.solved(),
+        report.scored()
+    );
+    if report.scored() < suite.len() {
+        println!(
+            "Unscored positions without bm or am: {}",
+            suite.len() - report.scored()
+        );
+    }
+    println!(
+        "Time to solutions (ms): {}",
+        report.solution_time.as_millis()
+    );
+    println!("Nodes searched: {}", report.nodes);
+    if report.max_points() > 0 {
+        println!("STS points: {} of {}", report.points(), report.max_points());
+    }
+
+    report
+}
+
+//////////////////
+//  Unit Tests  //
+//////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUITE: &str = r#"
+# Mate in one, back rank
+6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id "mate.001";
+6k1/5ppp/8/8/8/8/8/R5K1 w - - am Ra7; id "avoid.001";
+6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id "STS.001"; c0 "Ra8=10, Ra7=3";
+6k1/5ppp/8/8/8/8/8/R5K1 w - - id "unscored.001";
+"#;
+
+    #[test]
+    fn test_solve_suite() {
+        let mg = MoveGenerator::shared();
+        let tt = TranspositionTable::new(1);
+        let suite = parse_test_suite(SUITE, mg).unwrap();
+        assert_eq!(suite.len(), 4);
+
+        let limits = SearchLimits {
+            depth: 3,
+            ..Default::default()
+        };
+        let report = run_test_suite(&suite, mg, &tt, limits, 1);
+
+        assert_eq!(report.solved(), 3);
+        assert_eq!(report.scored(), 3);
+        assert!(!report.results[3].scored && !report.results[3].solved);
+        assert_eq!(report.results[0].id, "mate.001");
+        assert!(report.results[0].time_to_solution.is_some());
+        assert_eq!(report.results[0].points, None);
+        assert_eq!(report.points(), 10);
+        assert_eq!(report.max_points(), 10);
+    }
+
+    #[test]
+    fn test_sts_points() {
+        let mg = MoveGenerator::shared();
+        let epd = Epd::parse(
+            "6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8; c8 \"10 3\"; c9 \"a1a8 a1a7\";",
+            mg,
+        )
+        .unwrap();
+        let parse = |san| San::parse_move(san, &epd.board, mg).unwrap();
+
+        assert_eq!(sts_points(&epd, parse("Ra7"), mg), Some(3));
+        assert_eq!(sts_points(&epd, parse("Ra8"), mg), Some(10));
+        assert_eq!(sts_points(&epd, parse("Kf1"), mg), Some(0));
+        assert!(!is_solution(&epd, parse("Ra7")));
+    }
+}